@@ -0,0 +1,335 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use blurthing_core::{
+    Adjustment, Blur, Brightness, Contrast, FilterStep, HueRotate, Params, Saturation, WhiteBalance,
+};
+use pico_args::Arguments;
+
+use crate::manifest::Format;
+
+pub struct HashArgs {
+    pub params: Params,
+    pub paths: Vec<PathBuf>,
+}
+
+impl HashArgs {
+    pub fn parse(mut args: Arguments) -> Result<Self> {
+        let params = parse_params(&mut args)?;
+        let paths = parse_free_paths(args)?;
+        if paths.is_empty() {
+            anyhow::bail!("no input images provided");
+        }
+
+        Ok(Self { params, paths })
+    }
+}
+
+pub struct BatchArgs {
+    pub params: Params,
+    pub dir: PathBuf,
+    pub output: Option<PathBuf>,
+    pub format: Format,
+    pub jobs: Option<usize>,
+    pub cache: Option<PathBuf>,
+}
+
+impl BatchArgs {
+    pub fn parse(mut args: Arguments) -> Result<Self> {
+        let params = parse_params(&mut args)?;
+        let output: Option<PathBuf> = args.opt_value_from_str(["-o", "--output"])?;
+        let format = match args.opt_value_from_str("--format")? {
+            Some(format) => format,
+            None => output
+                .as_deref()
+                .and_then(Format::from_path)
+                .unwrap_or(Format::Json),
+        };
+        let jobs = args.opt_value_from_str(["-j", "--jobs"])?;
+        let cache = args.opt_value_from_str("--cache")?;
+        let dir = parse_single_path(args)?;
+
+        Ok(Self {
+            params,
+            dir,
+            output,
+            format,
+            jobs,
+            cache,
+        })
+    }
+}
+
+pub struct WatchArgs {
+    pub batch: BatchArgs,
+    pub debounce: Duration,
+}
+
+impl WatchArgs {
+    pub fn parse(mut args: Arguments) -> Result<Self> {
+        let debounce = args.opt_value_from_str("--debounce")?.unwrap_or(500);
+        let batch = BatchArgs::parse(args)?;
+        if batch.output.is_none() {
+            anyhow::bail!("an output manifest is required in watch mode");
+        }
+
+        Ok(Self {
+            batch,
+            debounce: Duration::from_millis(debounce),
+        })
+    }
+}
+
+pub struct DecodeArgs {
+    pub hash: String,
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub punch: f32,
+    pub quality: u8,
+}
+
+impl DecodeArgs {
+    pub fn parse(mut args: Arguments) -> Result<Self> {
+        let output = args.value_from_str(["-o", "--output"])?;
+        let width = args.opt_value_from_str("--width")?.unwrap_or(32);
+        let height = args.opt_value_from_str("--height")?.unwrap_or(32);
+        let punch = args.opt_value_from_str("--punch")?.unwrap_or(1.0);
+        let quality = args.opt_value_from_str("--quality")?.unwrap_or(90);
+
+        // The hash is not parsed as a path, since it can legitimately start with
+        // characters that look like an option.
+        let mut free = args.finish();
+        if free.len() != 1 {
+            anyhow::bail!("expected exactly one hash");
+        }
+        let hash = free
+            .remove(0)
+            .into_string()
+            .map_err(|_| anyhow::anyhow!("the hash contains invalid characters"))?;
+
+        if width == 0 || height == 0 {
+            anyhow::bail!("width and height must be greater than zero");
+        }
+
+        Ok(Self {
+            hash,
+            output,
+            width,
+            height,
+            punch,
+            quality,
+        })
+    }
+}
+
+pub struct ServeArgs {
+    pub listen: String,
+    pub jobs: Option<usize>,
+}
+
+impl ServeArgs {
+    pub fn parse(mut args: Arguments) -> Result<Self> {
+        let instance = Self {
+            listen: args
+                .opt_value_from_str("--listen")?
+                .unwrap_or_else(|| "127.0.0.1:7878".to_string()),
+            jobs: args.opt_value_from_str(["-j", "--jobs"])?,
+        };
+
+        if !parse_free_paths(args)?.is_empty() {
+            anyhow::bail!("unexpected positional argument");
+        }
+
+        Ok(instance)
+    }
+}
+
+pub struct VerifyArgs {
+    pub dir: PathBuf,
+    pub manifest: PathBuf,
+    pub format: Format,
+    pub jobs: Option<usize>,
+}
+
+impl VerifyArgs {
+    pub fn parse(mut args: Arguments) -> Result<Self> {
+        let manifest: PathBuf = args.value_from_str(["-m", "--manifest"])?;
+        let format = match args.opt_value_from_str("--format")? {
+            Some(format) => format,
+            None => Format::from_path(&manifest).unwrap_or(Format::Json),
+        };
+        let jobs = args.opt_value_from_str(["-j", "--jobs"])?;
+        let dir = parse_single_path(args)?;
+
+        Ok(Self {
+            dir,
+            manifest,
+            format,
+            jobs,
+        })
+    }
+}
+
+/// Parses the image manipulation parameters, falling back to the defaults used
+/// by the GUI application for the ones that are not specified.
+pub fn parse_params(args: &mut Arguments) -> Result<Params> {
+    let defaults = Params::default();
+
+    let rotation: i32 = args.opt_value_from_str("--rotate")?.unwrap_or(0);
+    if rotation % 90 != 0 {
+        anyhow::bail!("rotation must be a multiple of 90 degrees");
+    }
+
+    let params = Params {
+        components: (
+            args.opt_value_from_str(["-x", "--x-components"])?
+                .unwrap_or(defaults.components.0),
+            args.opt_value_from_str(["-y", "--y-components"])?
+                .unwrap_or(defaults.components.1),
+        ),
+        downsample_size: args
+            .opt_value_from_str("--downsample-size")?
+            .unwrap_or(defaults.downsample_size),
+        resampling: args
+            .opt_value_from_str("--resampling")?
+            .unwrap_or(defaults.resampling),
+        crop: args.opt_value_from_str("--crop")?,
+        matte: match args.opt_value_from_str::<_, String>("--matte")? {
+            Some(matte) => blurthing_core::parse_hex_color(&matte)?,
+            None => defaults.matte,
+        },
+        rotation: (rotation.rem_euclid(360) / 90) as i8,
+        flip_horizontal: args.contains("--flip-horizontal"),
+        flip_vertical: args.contains("--flip-vertical"),
+        aspect: args.opt_value_from_str("--aspect")?,
+        fit: args.opt_value_from_str("--fit")?.unwrap_or(defaults.fit),
+        filters: parse_filters(args)?,
+        linear: args.contains("--linear"),
+        punch: args
+            .opt_value_from_str("--punch")?
+            .unwrap_or(defaults.punch),
+    };
+
+    let (x, y) = params.components;
+    if !(1..=9).contains(&x) || !(1..=9).contains(&y) {
+        anyhow::bail!("components must be between 1 and 9");
+    }
+    if !(8..=1024).contains(&params.downsample_size) {
+        anyhow::bail!("the downsample size must be between 8 and 1024");
+    }
+    for step in &params.filters {
+        check_filter(&step.adjustment)?;
+    }
+
+    Ok(params)
+}
+
+/// Builds the filter stack from the order given by `--filters`, or the default
+/// one, with the settings of every filter taken from its own options.
+fn parse_filters(args: &mut Arguments) -> Result<Vec<FilterStep>> {
+    let smoothing = args.opt_value_from_str("--smoothing")?.unwrap_or_default();
+    let mut value =
+        |keys: &'static str| -> Result<i32> { Ok(args.opt_value_from_str(keys)?.unwrap_or(0)) };
+
+    let blur = Blur {
+        sigma: value("--blur")?,
+        method: smoothing,
+    };
+    let hue_rotate = HueRotate {
+        degrees: value("--hue")?,
+    };
+    let contrast = Contrast {
+        amount: value("--contrast")?,
+    };
+    let brightness = Brightness {
+        amount: value("--brightness")?,
+    };
+    let saturation = Saturation {
+        amount: value("--saturation")?,
+    };
+    let white_balance = WhiteBalance {
+        temperature: value("--temperature")?,
+        tint: value("--tint")?,
+    };
+
+    let order: String = args
+        .opt_value_from_str("--filters")?
+        .unwrap_or_else(|| FILTER_NAMES.join(","));
+
+    order
+        .split(',')
+        .map(|name| {
+            let adjustment = match name.trim() {
+                "blur" => Adjustment::Blur(blur),
+                "hue" => Adjustment::HueRotate(hue_rotate),
+                "contrast" => Adjustment::Contrast(contrast),
+                "brightness" => Adjustment::Brightness(brightness),
+                "saturation" => Adjustment::Saturation(saturation),
+                "white-balance" => Adjustment::WhiteBalance(white_balance),
+                _ => anyhow::bail!("unknown filter: {}", name.trim()),
+            };
+            Ok(FilterStep::new(adjustment))
+        })
+        .collect()
+}
+
+/// Checks that the settings of a filter are in the same ranges that the GUI
+/// application allows.
+fn check_filter(adjustment: &Adjustment) -> Result<()> {
+    let check = |name: &str, value: i32, min: i32, max: i32| {
+        if !(min..=max).contains(&value) {
+            anyhow::bail!("{} must be between {} and {}", name, min, max);
+        }
+        Ok(())
+    };
+
+    match adjustment {
+        Adjustment::Blur(blur) => check("blur", blur.sigma, 0, 128),
+        Adjustment::HueRotate(hue_rotate) => check("hue", hue_rotate.degrees, -180, 180),
+        Adjustment::Contrast(contrast) => check("contrast", contrast.amount, -40, 220),
+        Adjustment::Brightness(brightness) => check("brightness", brightness.amount, -100, 100),
+        Adjustment::Saturation(saturation) => check("saturation", saturation.amount, -100, 100),
+        Adjustment::WhiteBalance(white_balance) => {
+            check("temperature", white_balance.temperature, -100, 100)?;
+            check("tint", white_balance.tint, -100, 100)
+        }
+    }
+}
+
+/// Names of the filters accepted by `--filters`, in their default order.
+const FILTER_NAMES: [&str; 6] = [
+    "blur",
+    "hue",
+    "contrast",
+    "brightness",
+    "saturation",
+    "white-balance",
+];
+
+/// Takes the only remaining positional argument, rejecting unknown options.
+fn parse_single_path(args: Arguments) -> Result<PathBuf> {
+    let mut paths = parse_free_paths(args)?;
+    if paths.len() != 1 {
+        anyhow::bail!("expected exactly one input path");
+    }
+
+    Ok(paths.remove(0))
+}
+
+/// Collects the remaining positional arguments, rejecting unknown options.
+fn parse_free_paths(args: Arguments) -> Result<Vec<PathBuf>> {
+    args.finish()
+        .into_iter()
+        .map(|arg: OsString| {
+            let lossy = arg.to_string_lossy();
+            if lossy.starts_with('-') && lossy.len() > 1 {
+                anyhow::bail!("unknown option: {}", lossy);
+            }
+
+            Ok(PathBuf::from(arg))
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use blurthing_core::{Params, Pipeline};
+use rayon::prelude::*;
+
+use crate::args::BatchArgs;
+use crate::cache::{self, Cache, Cached};
+use crate::manifest::{self, Entry};
+use crate::utils;
+
+/// Result of hashing a single image of the batch.
+pub struct Hashed {
+    pub entry: Entry,
+    pub digest: String,
+    /// Whether the hash was taken from the cache instead of being computed.
+    pub hit: bool,
+}
+
+pub fn cmd_batch(args: BatchArgs) -> Result<()> {
+    let (entries, failed) = build_manifest(&args)?;
+    write_manifest(&args, &entries)?;
+    if let Some(output) = &args.output {
+        eprintln!("hashed {} images into {}", entries.len(), output.display());
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "failed to hash {} of {} images",
+            failed,
+            entries.len() + failed
+        );
+    }
+
+    Ok(())
+}
+
+/// Hashes every image in the batch directory, reporting the files that could not
+/// be hashed on stderr. Returns the entries and the number of failed images.
+pub fn build_manifest(args: &BatchArgs) -> Result<(Vec<Entry>, usize)> {
+    let images = utils::collect_images(&args.dir)?;
+    let cache = match &args.cache {
+        Some(path) => Cache::load(path)?,
+        None => Cache::default(),
+    };
+
+    let pool = utils::build_pool(args.jobs)?;
+
+    // Indexed parallel iterators collect in the original order, so the manifest
+    // is the same regardless of the order in which the workers finish.
+    let results: Vec<Result<Hashed>> = pool.install(|| {
+        images
+            .par_iter()
+            .map(|path| hash_entry(path, &args.dir, &args.params, &cache))
+            .collect()
+    });
+
+    let mut entries = Vec::with_capacity(results.len());
+    let mut cached = vec![];
+    let mut digests = HashSet::new();
+    let (mut hits, mut misses, mut failed) = (0, 0, 0);
+    for result in results {
+        match result {
+            Ok(hashed) => {
+                if hashed.hit {
+                    hits += 1;
+                } else {
+                    misses += 1;
+                }
+
+                // Identical files share the same cache item.
+                if digests.insert(hashed.digest.clone()) {
+                    cached.push(Cached {
+                        digest: hashed.digest,
+                        params: hashed.entry.params.clone(),
+                        hash: hashed.entry.hash.clone(),
+                        width: hashed.entry.width,
+                        height: hashed.entry.height,
+                    });
+                }
+
+                entries.push(hashed.entry);
+            }
+            Err(e) => {
+                eprintln!("{:#}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    if let Some(path) = &args.cache {
+        // Cache items that were not used in this run belong to images that were
+        // modified or removed, or were computed with other parameters.
+        let stale = cache.len()
+            - cached
+                .iter()
+                .filter(|c| cache.get(&c.digest, &c.params).is_some())
+                .count();
+        Cache::save(path, &cached)?;
+        eprintln!("cache: {} hits, {} misses, {} stale", hits, misses, stale);
+    }
+
+    Ok((entries, failed))
+}
+
+/// Writes the manifest to the output file of the batch, or to stdout.
+pub fn write_manifest(args: &BatchArgs, entries: &[Entry]) -> Result<()> {
+    match &args.output {
+        Some(output) => {
+            let file = File::create(output)
+                .with_context(|| format!("failed to create {}", output.display()))?;
+            manifest::write(entries, args.format, BufWriter::new(file))
+        }
+        None => manifest::write(entries, args.format, io::stdout().lock()),
+    }
+}
+
+/// Computes the manifest entry of the image at `path`, relative to `dir`, or
+/// takes it from the cache when the contents of the file did not change.
+pub fn hash_entry(path: &Path, dir: &Path, params: &Params, cache: &Cache) -> Result<Hashed> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let digest = cache::digest(&bytes);
+
+    let (hash, (width, height), hit) = match cache.get(&digest, params) {
+        Some(cached) => (cached.hash.clone(), (cached.width, cached.height), true),
+        None => {
+            let pipeline = Pipeline::from_memory(&bytes)
+                .with_context(|| format!("failed to load {}", path.display()))?;
+            let hash = pipeline
+                .encode(params)
+                .with_context(|| format!("failed to hash {}", path.display()))?;
+            (hash, pipeline.dimensions(), false)
+        }
+    };
+
+    let entry = Entry {
+        path: utils::relative_path(path, dir),
+        hash,
+        width,
+        height,
+        params: params.clone(),
+    };
+
+    Ok(Hashed { entry, digest, hit })
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use blurthing_core::Params;
+use serde::{Deserialize, Serialize};
+
+/// A hash computed in a previous run, identified by the digest of the image
+/// contents and the parameters it was computed with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cached {
+    pub digest: String,
+    pub params: Params,
+    pub hash: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct Cache {
+    entries: HashMap<(String, String), Cached>,
+}
+
+impl Cache {
+    /// Loads the cache stored at `path`, or an empty one if it does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).context("failed to open the cache file"),
+        };
+
+        let cached: Vec<Cached> = serde_json::from_reader(BufReader::new(file))
+            .context("failed to parse the cache file")?;
+
+        let mut entries = HashMap::with_capacity(cached.len());
+        for item in cached {
+            entries.insert(key(&item.digest, &item.params)?, item);
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Writes the given items to `path`, replacing any previous cache.
+    pub fn save(path: &Path, items: &[Cached]) -> Result<()> {
+        let file = File::create(path).context("failed to create the cache file")?;
+        serde_json::to_writer(BufWriter::new(file), items).context("failed to write the cache file")
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, digest: &str, params: &Params) -> Option<&Cached> {
+        self.entries.get(&key(digest, params).ok()?)
+    }
+}
+
+/// Computes the digest of the contents of an image file.
+pub fn digest(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
+fn key(digest: &str, params: &Params) -> Result<(String, String)> {
+    // The punch only affects decoding, so hashes computed with any punch can be
+    // reused for another one.
+    let params = Params {
+        punch: Params::default().punch,
+        ..params.clone()
+    };
+    Ok((digest.to_string(), serde_json::to_string(&params)?))
+}
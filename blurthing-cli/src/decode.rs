@@ -0,0 +1,9 @@
+use anyhow::{Context, Result};
+
+use crate::args::DecodeArgs;
+
+pub fn cmd_decode(args: DecodeArgs) -> Result<()> {
+    let img = blurthing_core::decode(&args.hash, args.width, args.height, args.punch)?;
+    blurthing_core::save_image(&img, &args.output, args.quality)
+        .with_context(|| format!("failed to write {}", args.output.display()))
+}
@@ -0,0 +1,31 @@
+use anyhow::Result;
+use blurthing_core::Pipeline;
+
+use crate::args::HashArgs;
+
+pub fn cmd_hash(args: HashArgs) -> Result<()> {
+    let mut failed = 0;
+
+    for path in &args.paths {
+        match Pipeline::open(path).and_then(|p| p.encode(&args.params)) {
+            // With a single input the bare hash is printed, so that the output
+            // can be captured directly by shell scripts.
+            Ok(hash) if args.paths.len() == 1 => println!("{}", hash),
+            Ok(hash) => println!("{}\t{}", hash, path.display()),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "failed to compute {} of {} hashes",
+            failed,
+            args.paths.len()
+        );
+    }
+
+    Ok(())
+}
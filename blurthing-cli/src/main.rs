@@ -0,0 +1,139 @@
+mod args;
+mod batch;
+mod cache;
+mod decode;
+mod hash;
+mod manifest;
+mod serve;
+mod utils;
+mod verify;
+mod watch;
+
+use anyhow::{Context, Result};
+use pico_args::Arguments;
+
+use args::{BatchArgs, DecodeArgs, HashArgs, ServeArgs, VerifyArgs, WatchArgs};
+
+const HELP: &str = "\
+Compute BlurHashes from image files.
+
+USAGE:
+  blurthing-cli <COMMAND> [OPTIONS]
+
+COMMANDS:
+  hash [OPTIONS] <IMAGES>...    Print the BlurHash of each image
+  batch [OPTIONS] <DIR>         Hash every image in a directory into a manifest
+  watch [OPTIONS] <DIR>         Keep a manifest up to date while the images change
+  verify [OPTIONS] <DIR>        Check that a manifest matches the images in a directory
+  decode [OPTIONS] <HASH>       Render a hash to an image file
+  serve [OPTIONS]               Hash uploaded images over a local HTTP service
+
+PARAMETERS:
+  -x, --x-components <N>        Number of samples in the horizontal axis [default: 4]
+  -y, --y-components <N>        Number of samples in the vertical axis [default: 3]
+  --downsample-size <N>         Size the image is downsampled to before processing [default: 128]
+  --resampling <FILTER>         Downsampling filter, out of nearest, triangle, catmullrom,
+                                gaussian and lanczos3 [default: lanczos3]
+  --crop <X,Y,W,H>              Region to hash, in fractions of the image size [default: all]
+  --matte <RRGGBB>              Color that transparency is composited over [default: ffffff]
+  --rotate <DEGREES>            Clockwise rotation, a multiple of 90 [default: 0]
+  --flip-horizontal             Mirror the image horizontally, after rotating it
+  --flip-vertical               Mirror the image vertically, after rotating it
+  --aspect <W:H>                Aspect ratio the image is fitted to [default: the image's]
+  --fit <cover|contain>         How the image is fitted to the aspect ratio [default: cover]
+  --blur <N>                    Amount of blur applied before hashing, from 0 to 128 [default: 0]
+  --smoothing <METHOD>          Blur method, out of gaussian, bilateral and median, the
+                                last two with a radius in source pixels [default: gaussian]
+  --hue <DEGREES>               Rotation of the hue of the image, from -180 to 180 [default: 0]
+  --brightness <N>              Brightness adjustment, from -100 to 100 [default: 0]
+  --contrast <N>                Contrast adjustment, from -40 to 220 [default: 0]
+  --saturation <N>              Saturation adjustment, from -100 to 100 [default: 0]
+  --temperature <N>             Warmth adjustment, from -100 to 100 [default: 0]
+  --tint <N>                    Green (positive) or magenta tint, from -100 to 100 [default: 0]
+  --filters <LIST>              Comma-separated order of the filters, out of blur, hue,
+                                contrast, brightness, saturation and white-balance
+                                [default: all of them, in this order]
+  --linear                      Apply the adjustments in linear light
+  --punch <N>                   Contrast of the decoded image, kept in manifests [default: 1.0]
+
+BATCH OPTIONS:
+  -o, --output <FILE>           Write the manifest to a file instead of stdout
+  --format <json|csv>           Manifest format [default: from the output extension, or json]
+  -j, --jobs <N>                Number of images hashed in parallel [default: all cores]
+  --cache <FILE>                Reuse the hashes of unchanged images from a cache file
+
+WATCH OPTIONS:
+  Accepts the batch options, with a required output manifest.
+  --debounce <MS>               Time to wait for a burst of changes to settle [default: 500]
+
+VERIFY OPTIONS:
+  -m, --manifest <FILE>         Manifest produced by a previous batch run
+  --format <json|csv>           Manifest format [default: from the manifest extension, or json]
+  -j, --jobs <N>                Number of images hashed in parallel [default: all cores]
+
+DECODE OPTIONS:
+  -o, --output <FILE>           Image to write, in jpg, png or webp format
+  --width <N>                   Width of the rendered image [default: 32]
+  --height <N>                  Height of the rendered image [default: 32]
+  --punch <N>                   Contrast of the rendered image [default: 1.0]
+  --quality <N>                 Quality of JPEG images, from 1 to 100 [default: 90]
+
+SERVE OPTIONS:
+  --listen <ADDR>               Address to listen on [default: 127.0.0.1:7878]
+  -j, --jobs <N>                Number of requests handled in parallel [default: all cores]
+
+  POST /hash?<PARAMETERS>       Hash the image in the body (raw or multipart), using the
+                                long names of the parameters above as query fields
+  GET /decode?hash=<HASH>       Render a hash to a PNG, with optional width, height and
+                                punch query fields [default: 32, 32, 1.0]
+
+OPTIONS:
+  -h, --help                    Print this message
+";
+
+fn run() -> Result<()> {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        print!("{}", HELP);
+        return Ok(());
+    }
+
+    match args
+        .subcommand()?
+        .context("no subcommand provided, see --help")?
+        .as_str()
+    {
+        "hash" => {
+            let cmd_args = HashArgs::parse(args)?;
+            hash::cmd_hash(cmd_args)
+        }
+        "batch" => {
+            let cmd_args = BatchArgs::parse(args)?;
+            batch::cmd_batch(cmd_args)
+        }
+        "watch" => {
+            let cmd_args = WatchArgs::parse(args)?;
+            watch::cmd_watch(cmd_args)
+        }
+        "decode" => {
+            let cmd_args = DecodeArgs::parse(args)?;
+            decode::cmd_decode(cmd_args)
+        }
+        "serve" => {
+            let cmd_args = ServeArgs::parse(args)?;
+            serve::cmd_serve(cmd_args)
+        }
+        "verify" => {
+            let cmd_args = VerifyArgs::parse(args)?;
+            verify::cmd_verify(cmd_args)
+        }
+        _ => anyhow::bail!("unknown subcommand, see --help"),
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
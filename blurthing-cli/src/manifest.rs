@@ -0,0 +1,169 @@
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use blurthing_core::Params;
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+/// A single image listed in a manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Path of the image relative to the batch directory, with `/` separators.
+    pub path: String,
+    pub hash: String,
+    pub width: u32,
+    pub height: u32,
+    pub params: Params,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// Guesses the manifest format from the extension of the given path.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        extension.parse().ok()
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => anyhow::bail!("unknown manifest format: {}", s),
+        }
+    }
+}
+
+const CSV_HEADERS: [&str; 5] = ["path", "hash", "width", "height", "params"];
+
+pub fn write(entries: &[Entry], format: Format, writer: impl Write) -> Result<()> {
+    match format {
+        Format::Json => write_json(entries, writer),
+        Format::Csv => write_csv(entries, writer),
+    }
+}
+
+fn write_json(entries: &[Entry], mut writer: impl Write) -> Result<()> {
+    serde_json::to_writer_pretty(&mut writer, entries)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn write_csv(entries: &[Entry], writer: impl Write) -> Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(CSV_HEADERS)?;
+
+    for entry in entries {
+        // The parameters are stored as a JSON object in a single column, so that
+        // the layout of the file does not change when new parameters are added.
+        csv.write_record([
+            entry.path.clone(),
+            entry.hash.clone(),
+            entry.width.to_string(),
+            entry.height.to_string(),
+            serde_json::to_string(&entry.params)?,
+        ])?;
+    }
+
+    csv.flush()?;
+    Ok(())
+}
+
+pub fn read(format: Format, reader: impl Read) -> Result<Vec<Entry>> {
+    match format {
+        Format::Json => serde_json::from_reader(reader).context("failed to parse the manifest"),
+        Format::Csv => read_csv(reader),
+    }
+}
+
+fn read_csv(reader: impl Read) -> Result<Vec<Entry>> {
+    let mut csv = csv::Reader::from_reader(reader);
+    if csv.headers()?.iter().ne(CSV_HEADERS) {
+        anyhow::bail!("the manifest does not have the expected columns");
+    }
+
+    let mut entries = vec![];
+    for (i, record) in csv.records().enumerate() {
+        let entry = parse_csv_record(&record?)
+            .with_context(|| format!("invalid manifest entry on line {}", i + 2))?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn parse_csv_record(record: &StringRecord) -> Result<Entry> {
+    let field = |index: usize| record.get(index).unwrap_or_default();
+
+    Ok(Entry {
+        path: field(0).to_string(),
+        hash: field(1).to_string(),
+        width: field(2).parse()?,
+        height: field(3).parse()?,
+        params: serde_json::from_str(field(4))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use blurthing_core::{Adjustment, Aspect, Blur, FilterStep, Smoothing};
+
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        let mut params = Params {
+            aspect: Some(Aspect::new(16, 9)),
+            linear: true,
+            punch: 1.5,
+            ..Params::default()
+        };
+        params.filters.insert(
+            0,
+            FilterStep::new(Adjustment::Blur(Blur {
+                sigma: 12,
+                method: Smoothing::Median,
+            })),
+        );
+
+        vec![
+            Entry {
+                path: "photos/a, \"quoted\".jpg".to_string(),
+                hash: "LeKn#kjF~pozkDjsjsa}~Vj]ozjs".to_string(),
+                width: 1024,
+                height: 768,
+                params,
+            },
+            Entry {
+                path: "b.png".to_string(),
+                hash: "|jK-:^ad".to_string(),
+                width: 1,
+                height: 1,
+                params: Params::default(),
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trips_manifests() {
+        for format in [Format::Json, Format::Csv] {
+            let mut buffer = vec![];
+            write(&entries(), format, &mut buffer).unwrap();
+            assert_eq!(read(format, buffer.as_slice()).unwrap(), entries());
+        }
+    }
+
+    #[test]
+    fn rejects_csv_with_other_columns() {
+        assert!(read(Format::Csv, &b"path,hash\na.png,LeKn#k\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use std::io::{Cursor, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+use anyhow::{Context, Result};
+use blurthing_core::Pipeline;
+use image::ImageFormat;
+use pico_args::Arguments;
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+use crate::args::{self, ServeArgs};
+
+/// Largest request body that is accepted, to avoid exhausting the memory.
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+/// Largest width or height of the images rendered by the decode endpoint.
+const MAX_DECODE_SIZE: u32 = 4096;
+/// Query fields that map to the switches of the CLI.
+const FLAG_FIELDS: [&str; 3] = ["flip-horizontal", "flip-vertical", "linear"];
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// An error that is reported to the client with the given status code.
+struct HttpError(u16, String);
+
+impl<E: Into<anyhow::Error>> From<E> for HttpError {
+    fn from(e: E) -> Self {
+        Self(400, format!("{:#}", e.into()))
+    }
+}
+
+pub fn cmd_serve(args: ServeArgs) -> Result<()> {
+    let server = Server::http(&args.listen)
+        .map_err(|e| anyhow::anyhow!("failed to listen on {}: {}", args.listen, e))?;
+    eprintln!("listening on http://{}", args.listen);
+
+    let workers = args
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                for request in server.incoming_requests() {
+                    handle_request(request);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_request(mut request: Request) {
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.url().to_string(), String::new()),
+    };
+
+    // A panic while handling a request is reported to the client, instead of
+    // taking down the worker thread and with it the whole server.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        match (request.method(), path.as_str()) {
+            (Method::Post, "/hash") => handle_hash(&mut request, &query),
+            (Method::Get, "/decode") => handle_decode(&query),
+            (_, "/hash" | "/decode") => Err(HttpError(405, "method not allowed".to_string())),
+            _ => Err(HttpError(404, "not found".to_string())),
+        }
+    }))
+    .unwrap_or_else(|_| Err(HttpError(500, "internal server error".to_string())));
+
+    let response = result.unwrap_or_else(|HttpError(status, message)| {
+        json_response(json!({ "error": message })).with_status_code(StatusCode(status))
+    });
+
+    if let Err(e) = request.respond(response) {
+        eprintln!("failed to send the response: {}", e);
+    }
+}
+
+/// Computes the BlurHash of the image in the request body, either sent as is or
+/// as the first file of a multipart form.
+fn handle_hash(request: &mut Request, query: &str) -> Result<HttpResponse, HttpError> {
+    let mut query_args = query_arguments(query)?;
+    let params = args::parse_params(&mut query_args)?;
+    ensure_consumed(query_args)?;
+
+    let body = read_body(request)?;
+    let boundary = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .and_then(|h| multipart_boundary(h.value.as_str()));
+    let bytes = match boundary {
+        Some(boundary) => multipart_file(&body, &boundary)
+            .ok_or_else(|| HttpError(400, "no file found in the multipart body".to_string()))?,
+        None => &body,
+    };
+
+    let pipeline = Pipeline::from_memory(bytes).context("failed to load the image")?;
+    let hash = pipeline.encode(&params)?;
+    let [r, g, b] = blurthing_core::average_color(&hash)?;
+    let (width, height) = pipeline.dimensions();
+
+    Ok(json_response(json!({
+        "hash": hash,
+        "width": width,
+        "height": height,
+        "average_color": format!("#{:02x}{:02x}{:02x}", r, g, b),
+    })))
+}
+
+/// Renders a BlurHash to a PNG image of the requested size.
+fn handle_decode(query: &str) -> Result<HttpResponse, HttpError> {
+    let mut query_args = query_arguments(query)?;
+    let hash: String = query_args.value_from_str("--hash")?;
+    let width = query_args.opt_value_from_str("--width")?.unwrap_or(32);
+    let height = query_args.opt_value_from_str("--height")?.unwrap_or(32);
+    let punch = query_args.opt_value_from_str("--punch")?.unwrap_or(1.0);
+    ensure_consumed(query_args)?;
+
+    if !(1..=MAX_DECODE_SIZE).contains(&width) || !(1..=MAX_DECODE_SIZE).contains(&height) {
+        return Err(HttpError(
+            400,
+            format!("width and height must be between 1 and {}", MAX_DECODE_SIZE),
+        ));
+    }
+
+    let img = blurthing_core::decode(&hash, width, height, punch)?;
+    let mut png = Cursor::new(vec![]);
+    img.write_to(&mut png, ImageFormat::Png)
+        .context("failed to encode the decoded image")?;
+
+    Ok(Response::from_data(png.into_inner()).with_header(content_type("image/png")))
+}
+
+fn read_body(request: &mut Request) -> Result<Vec<u8>, HttpError> {
+    if request.body_length().unwrap_or(0) > MAX_BODY_SIZE {
+        return Err(HttpError(413, "the request body is too large".to_string()));
+    }
+
+    let mut body = vec![];
+    request
+        .as_reader()
+        .take(MAX_BODY_SIZE as u64 + 1)
+        .read_to_end(&mut body)
+        .context("failed to read the request body")?;
+
+    if body.len() > MAX_BODY_SIZE {
+        return Err(HttpError(413, "the request body is too large".to_string()));
+    }
+
+    Ok(body)
+}
+
+/// Converts the query string to command line style arguments, so that the query
+/// fields are parsed and validated exactly like the options of the CLI.
+fn query_arguments(query: &str) -> Result<Arguments> {
+    let mut args = vec![];
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (key, value) = (percent_decode(key)?, percent_decode(value)?);
+
+        // Switches take no value on the command line, so they are only passed
+        // along when they are turned on.
+        if FLAG_FIELDS.contains(&key.as_str()) {
+            match value.as_str() {
+                "" | "true" => args.push(format!("--{}", key).into()),
+                "false" => {}
+                _ => anyhow::bail!("invalid value for {}: expected true or false", key),
+            }
+            continue;
+        }
+
+        args.push(format!("--{}", key).into());
+        args.push(value.into());
+    }
+
+    Ok(Arguments::from_vec(args))
+}
+
+fn ensure_consumed(args: Arguments) -> Result<()> {
+    let remaining = args.finish();
+    if let Some(arg) = remaining
+        .iter()
+        .find(|a| a.to_string_lossy().starts_with("--"))
+    {
+        anyhow::bail!(
+            "unknown or repeated query field: {}",
+            arg.to_string_lossy().trim_start_matches('-')
+        );
+    }
+
+    Ok(())
+}
+
+/// Decodes the `%XX` escapes of a query component. Plus signs are kept as they
+/// are, since they are valid BlurHash characters.
+fn percent_decode(s: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next().unwrap_or(0), iter.next().unwrap_or(0)];
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                anyhow::bail!("invalid escape in the query");
+            }
+            let hex = std::str::from_utf8(&hex)?;
+            bytes.push(u8::from_str_radix(hex, 16)?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    String::from_utf8(bytes).context("invalid characters in the query")
+}
+
+fn multipart_boundary(content_type: &str) -> Option<String> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        (key.trim() == "boundary").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Returns the contents of the first file in a multipart body, or of the first
+/// part if none of them has a file name.
+fn multipart_file<'a>(body: &'a [u8], boundary: &str) -> Option<&'a [u8]> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = vec![];
+
+    let mut rest = body;
+    while let Some(start) = find(rest, &delimiter) {
+        rest = &rest[start + delimiter.len()..];
+        if rest.starts_with(b"--") {
+            break;
+        }
+
+        let end = find(rest, &delimiter).unwrap_or(rest.len());
+        let part = rest[..end].strip_prefix(b"\r\n").unwrap_or(&rest[..end]);
+        let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+
+        let split = find(part, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&part[..split]);
+        parts.push((headers.contains("filename="), &part[split + 4..]));
+    }
+
+    parts
+        .iter()
+        .find(|(is_file, _)| *is_file)
+        .or(parts.first())
+        .map(|(_, contents)| *contents)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn json_response(value: serde_json::Value) -> HttpResponse {
+    Response::from_string(value.to_string()).with_header(content_type("application/json"))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query_params(query: &str) -> Result<blurthing_core::Params> {
+        let mut query_args = query_arguments(query)?;
+        let params = args::parse_params(&mut query_args)?;
+        ensure_consumed(query_args)?;
+        Ok(params)
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("%3A%2f%25").unwrap(), ":/%");
+        assert_eq!(percent_decode("LeKn+kjF").unwrap(), "LeKn+kjF");
+        assert!(percent_decode("%4").is_err());
+        assert!(percent_decode("abc%").is_err());
+        assert!(percent_decode("%+1").is_err());
+        assert!(percent_decode("%zz").is_err());
+        assert!(percent_decode("%ff").is_err());
+    }
+
+    #[test]
+    fn parses_boolean_fields() {
+        assert!(!query_params("").unwrap().linear);
+        assert!(query_params("linear").unwrap().linear);
+        assert!(query_params("linear=true").unwrap().linear);
+        assert!(!query_params("linear=false").unwrap().linear);
+        assert!(
+            !query_params("flip-horizontal=false")
+                .unwrap()
+                .flip_horizontal
+        );
+        assert!(query_params("flip-vertical=true").unwrap().flip_vertical);
+        assert!(query_params("linear=yes").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_and_repeated_fields() {
+        assert_eq!(
+            query_params("blur=3&x-components=4").unwrap(),
+            query_params("x-components=4&blur=3").unwrap()
+        );
+        assert!(query_params("sharpen=3").is_err());
+        assert!(query_params("blur=3&blur=5").is_err());
+        assert!(query_params("blur=-1").is_err());
+    }
+
+    #[test]
+    fn finds_the_uploaded_file() {
+        let body = b"--xyz\r\n\
+            Content-Disposition: form-data; name=\"note\"\r\n\r\n\
+            hello\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"image\"; filename=\"a.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            \x89PNG\r\n\x1a\n\r\n\
+            --xyz--\r\n";
+        assert_eq!(multipart_file(body, "xyz"), Some(&b"\x89PNG\r\n\x1a\n"[..]));
+
+        let body =
+            b"--xyz\r\nContent-Disposition: form-data; name=\"image\"\r\n\r\nbytes\r\n--xyz--";
+        assert_eq!(multipart_file(body, "xyz"), Some(&b"bytes"[..]));
+
+        assert_eq!(multipart_file(b"no parts here", "xyz"), None);
+        assert_eq!(
+            multipart_boundary("multipart/form-data; boundary=\"xyz\""),
+            Some("xyz".to_string())
+        );
+        assert_eq!(multipart_boundary("image/png"), None);
+    }
+}
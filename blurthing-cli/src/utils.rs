@@ -0,0 +1,50 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use blurthing_core::ALLOWED_EXTENSIONS;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use walkdir::WalkDir;
+
+/// Returns whether the file at the given path has a supported image extension.
+pub fn is_supported_image(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    ALLOWED_EXTENSIONS.contains(&extension.as_str())
+}
+
+/// Recursively collects the supported images in a directory, sorted by path.
+pub fn collect_images(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut images = vec![];
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.context("failed to read the input directory")?;
+        if entry.file_type().is_file() && is_supported_image(entry.path()) {
+            images.push(entry.into_path());
+        }
+    }
+
+    Ok(images)
+}
+
+/// Formats `path` relative to `base`, always using `/` as the separator so that
+/// manifests are the same on every platform.
+pub fn relative_path(path: &Path, base: &Path) -> String {
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Creates the pool that images are hashed on, using all cores by default.
+pub fn build_pool(jobs: Option<usize>) -> Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .context("failed to create the worker pool")
+}
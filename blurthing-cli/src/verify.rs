@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+
+use crate::args::VerifyArgs;
+use crate::batch;
+use crate::cache::Cache;
+use crate::manifest::{self, Entry};
+use crate::utils;
+
+/// Difference between a manifest entry and the image it refers to.
+enum Change {
+    Unchanged,
+    Modified(Entry),
+    Missing,
+    Failed(anyhow::Error),
+}
+
+pub fn cmd_verify(args: VerifyArgs) -> Result<()> {
+    let file = File::open(&args.manifest)
+        .with_context(|| format!("failed to open {}", args.manifest.display()))?;
+    let entries = manifest::read(args.format, BufReader::new(file))?;
+
+    let pool = utils::build_pool(args.jobs)?;
+    let cache = Cache::default();
+    let changes: Vec<Change> = pool.install(|| {
+        entries
+            .par_iter()
+            .map(|entry| {
+                let path = args.dir.join(&entry.path);
+                if !path.is_file() {
+                    return Change::Missing;
+                }
+
+                match batch::hash_entry(&path, &args.dir, &entry.params, &cache) {
+                    Ok(hashed) if hashed.entry == *entry => Change::Unchanged,
+                    Ok(hashed) => Change::Modified(hashed.entry),
+                    Err(e) => Change::Failed(e),
+                }
+            })
+            .collect()
+    });
+
+    let listed: HashSet<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    let unlisted: Vec<String> = utils::collect_images(&args.dir)?
+        .iter()
+        .map(|path| utils::relative_path(path, &args.dir))
+        .filter(|path| !listed.contains(path.as_str()))
+        .collect();
+
+    let (mut modified, mut missing, mut failed) = (0, 0, 0);
+    for (entry, change) in entries.iter().zip(changes) {
+        match change {
+            Change::Unchanged => {}
+            Change::Modified(current) => {
+                println!("~ {}", entry.path);
+                if current.hash != entry.hash {
+                    println!("    hash: {} -> {}", entry.hash, current.hash);
+                }
+                if (current.width, current.height) != (entry.width, entry.height) {
+                    println!(
+                        "    size: {}x{} -> {}x{}",
+                        entry.width, entry.height, current.width, current.height
+                    );
+                }
+                modified += 1;
+            }
+            Change::Missing => {
+                println!("- {} (missing)", entry.path);
+                missing += 1;
+            }
+            Change::Failed(e) => {
+                println!("! {} ({:#})", entry.path, e);
+                failed += 1;
+            }
+        }
+    }
+
+    for path in &unlisted {
+        println!("+ {} (not in manifest)", path);
+    }
+
+    if modified + missing + failed + unlisted.len() > 0 {
+        anyhow::bail!(
+            "the manifest is out of date: {} changed, {} missing, {} unlisted, {} failed",
+            modified,
+            missing,
+            unlisted.len(),
+            failed
+        );
+    }
+
+    eprintln!("the manifest is up to date ({} images)", entries.len());
+    Ok(())
+}
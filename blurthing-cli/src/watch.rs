@@ -0,0 +1,116 @@
+use std::sync::mpsc;
+
+use anyhow::{Context, Result};
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+
+use crate::args::WatchArgs;
+use crate::batch;
+use crate::cache::Cache;
+use crate::manifest::Entry;
+use crate::utils;
+
+pub fn cmd_watch(mut args: WatchArgs) -> Result<()> {
+    // Events are reported with absolute paths, which must share the prefix of the
+    // batch directory for the manifest paths to be computed correctly.
+    args.batch.dir = args
+        .batch
+        .dir
+        .canonicalize()
+        .context("failed to resolve the input directory")?;
+
+    // The watcher is started before the initial run, so that the changes made
+    // while it is in progress are queued and applied right after it.
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer =
+        new_debouncer(args.debounce, tx).context("failed to create the file watcher")?;
+    debouncer
+        .watcher()
+        .watch(&args.batch.dir, RecursiveMode::Recursive)
+        .context("failed to watch the input directory")?;
+
+    let (mut entries, _) = batch::build_manifest(&args.batch)?;
+    batch::write_manifest(&args.batch, &entries)?;
+    eprintln!(
+        "hashed {} images, watching {} for changes",
+        entries.len(),
+        args.batch.dir.display()
+    );
+
+    let cache = Cache::default();
+    for result in rx {
+        let events = match result {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("watch error: {}", e);
+                continue;
+            }
+        };
+
+        let mut changed = false;
+        for event in events {
+            let path = event.path;
+            let relative = utils::relative_path(&path, &args.batch.dir);
+
+            if !path.exists() {
+                // Removing a directory removes all the images it contained.
+                let prefix = format!("{}/", relative);
+                let count = entries.len();
+                entries.retain(|e| e.path != relative && !e.path.starts_with(&prefix));
+                if entries.len() != count {
+                    eprintln!("removed {}", relative);
+                    changed = true;
+                }
+                continue;
+            }
+
+            let images = if path.is_dir() {
+                match utils::collect_images(&path) {
+                    Ok(images) => images,
+                    Err(e) => {
+                        eprintln!("{:#}", e);
+                        continue;
+                    }
+                }
+            } else if utils::is_supported_image(&path) {
+                vec![path]
+            } else {
+                continue;
+            };
+
+            for image in images {
+                match batch::hash_entry(&image, &args.batch.dir, &args.batch.params, &cache) {
+                    Ok(hashed) => changed |= upsert(&mut entries, hashed.entry),
+                    Err(e) => eprintln!("{:#}", e),
+                }
+            }
+        }
+
+        if changed {
+            if let Err(e) = batch::write_manifest(&args.batch, &entries) {
+                eprintln!("failed to write the manifest: {:#}", e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Inserts or replaces the entry with the same path, keeping the entries in the
+/// same order as a full batch run. Returns whether the manifest was modified.
+fn upsert(entries: &mut Vec<Entry>, entry: Entry) -> bool {
+    let position = entries.binary_search_by(|e| e.path.split('/').cmp(entry.path.split('/')));
+    match position {
+        Ok(index) if entries[index] == entry => false,
+        Ok(index) => {
+            eprintln!("updated {}", entry.path);
+            entries[index] = entry;
+            true
+        }
+        Err(index) => {
+            eprintln!("added {}", entry.path);
+            entries.insert(index, entry);
+            true
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+
+use crate::EXPORT_EXTENSIONS;
+
+/// Writes an image to a file, picking the format from the extension of `path`.
+/// The quality, from 1 to 100, is only used by lossy formats (JPEG).
+pub fn save_image(img: &DynamicImage, path: &Path, quality: u8) -> Result<()> {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !EXPORT_EXTENSIONS.contains(&extension.as_str()) {
+        anyhow::bail!(
+            "unsupported output format, expected one of: {}",
+            EXPORT_EXTENSIONS.join(", ")
+        );
+    }
+
+    let rgb = img.to_rgb8();
+    let result = match extension.as_str() {
+        "jpg" | "jpeg" => {
+            let file = File::create(path)?;
+            JpegEncoder::new_with_quality(BufWriter::new(file), quality.clamp(1, 100))
+                .encode_image(&rgb)
+        }
+        _ => rgb.save(path),
+    };
+
+    result.map_err(|e| anyhow!(e.to_string().to_lowercase()))
+}
@@ -0,0 +1,405 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use image::{DynamicImage, Rgba32FImage};
+
+/// An adjustment applied to the downsampled image before it is hashed.
+pub trait Filter {
+    /// Name of the filter, as shown to the user.
+    fn name(&self) -> &'static str;
+
+    /// Applies the filter to the downsampled image, whose size relative to the
+    /// source image at its original resolution is `scale`.
+    fn apply(&self, img: &DynamicImage, scale: f32) -> DynamicImage;
+}
+
+/// Smoothing of the image, to keep only its overall shapes and colors.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Blur {
+    /// Standard deviation of the Gaussian blur in pixels of the downsampled image,
+    /// or radius of the other methods in pixels of the source image.
+    pub sigma: i32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub method: Smoothing,
+}
+
+impl Filter for Blur {
+    fn name(&self) -> &'static str {
+        "Smoothness"
+    }
+
+    fn apply(&self, img: &DynamicImage, scale: f32) -> DynamicImage {
+        if self.sigma <= 0 {
+            return img.clone();
+        }
+
+        match self.method {
+            Smoothing::Gaussian => img.blur(self.sigma as f32),
+            Smoothing::Bilateral => bilateral(img, self.sigma as f32 * scale),
+            Smoothing::Median => median(img, self.sigma as f32 * scale),
+        }
+    }
+}
+
+/// Algorithm used to smooth the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Smoothing {
+    #[default]
+    Gaussian,
+    /// Averages the pixels with the similar ones around them, keeping the edges
+    /// between different areas sharp.
+    Bilateral,
+    /// Replaces every pixel with the median of the ones around it, which removes
+    /// the fine details but keeps the edges.
+    Median,
+}
+
+impl Smoothing {
+    pub const ALL: [Self; 3] = [Self::Gaussian, Self::Bilateral, Self::Median];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gaussian => "Gaussian",
+            Self::Bilateral => "Bilateral",
+            Self::Median => "Median",
+        }
+    }
+}
+
+impl FromStr for Smoothing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "gaussian" => Ok(Self::Gaussian),
+            "bilateral" => Ok(Self::Bilateral),
+            "median" => Ok(Self::Median),
+            _ => anyhow::bail!("unknown smoothing method: {}", s),
+        }
+    }
+}
+
+/// Rotation of the hue of every pixel, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HueRotate {
+    pub degrees: i32,
+}
+
+impl Filter for HueRotate {
+    fn name(&self) -> &'static str {
+        "Hue Rotation"
+    }
+
+    fn apply(&self, img: &DynamicImage, _scale: f32) -> DynamicImage {
+        img.huerotate(self.degrees)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Contrast {
+    pub amount: i32,
+}
+
+impl Filter for Contrast {
+    fn name(&self) -> &'static str {
+        "Contrast"
+    }
+
+    fn apply(&self, img: &DynamicImage, _scale: f32) -> DynamicImage {
+        img.adjust_contrast(self.amount as f32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Brightness {
+    pub amount: i32,
+}
+
+impl Filter for Brightness {
+    fn name(&self) -> &'static str {
+        "Brightness"
+    }
+
+    fn apply(&self, img: &DynamicImage, _scale: f32) -> DynamicImage {
+        let value = self.amount * 2;
+        match img {
+            // The `image` implementation only adds whole numbers to the channels,
+            // which does not work with floating point values between 0 and 1.
+            DynamicImage::ImageRgba32F(_) => {
+                map_colors(img, |rgb| rgb.map(|c| c + value as f32 / 255.0))
+            }
+            _ => img.brighten(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Saturation {
+    pub amount: i32,
+}
+
+impl Filter for Saturation {
+    fn name(&self) -> &'static str {
+        "Saturation"
+    }
+
+    fn apply(&self, img: &DynamicImage, _scale: f32) -> DynamicImage {
+        if self.amount == 0 {
+            return img.clone();
+        }
+
+        let amount = 1.0 + self.amount as f32 / 100.0;
+        map_colors(img, |[r, g, b]| {
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            [r, g, b].map(|c| luma + (c - luma) * amount)
+        })
+    }
+}
+
+/// White balance shifts, towards warmer (yellow) or greener colors when positive
+/// and towards colder (blue) or magenta ones when negative.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WhiteBalance {
+    pub temperature: i32,
+    pub tint: i32,
+}
+
+impl Filter for WhiteBalance {
+    fn name(&self) -> &'static str {
+        "White Balance"
+    }
+
+    fn apply(&self, img: &DynamicImage, _scale: f32) -> DynamicImage {
+        if self.temperature == 0 && self.tint == 0 {
+            return img.clone();
+        }
+
+        let temperature = self.temperature as f32 / 500.0;
+        let tint = self.tint as f32 / 500.0;
+        map_colors(img, |[r, g, b]| {
+            [
+                r * (1.0 + temperature),
+                g * (1.0 + tint),
+                b * (1.0 - temperature),
+            ]
+        })
+    }
+}
+
+/// Any of the available filters, which can be stored in the parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Adjustment {
+    Blur(Blur),
+    HueRotate(HueRotate),
+    Contrast(Contrast),
+    Brightness(Brightness),
+    Saturation(Saturation),
+    WhiteBalance(WhiteBalance),
+}
+
+impl Adjustment {
+    /// Every kind of filter with its neutral settings, in the default order.
+    pub const ALL: [Self; 6] = [
+        Self::Blur(Blur {
+            sigma: 0,
+            method: Smoothing::Gaussian,
+        }),
+        Self::HueRotate(HueRotate { degrees: 0 }),
+        Self::Contrast(Contrast { amount: 0 }),
+        Self::Brightness(Brightness { amount: 0 }),
+        Self::Saturation(Saturation { amount: 0 }),
+        Self::WhiteBalance(WhiteBalance {
+            temperature: 0,
+            tint: 0,
+        }),
+    ];
+
+    pub fn as_filter(&self) -> &dyn Filter {
+        match self {
+            Self::Blur(filter) => filter,
+            Self::HueRotate(filter) => filter,
+            Self::Contrast(filter) => filter,
+            Self::Brightness(filter) => filter,
+            Self::Saturation(filter) => filter,
+            Self::WhiteBalance(filter) => filter,
+        }
+    }
+}
+
+impl Filter for Adjustment {
+    fn name(&self) -> &'static str {
+        self.as_filter().name()
+    }
+
+    fn apply(&self, img: &DynamicImage, scale: f32) -> DynamicImage {
+        self.as_filter().apply(img, scale)
+    }
+}
+
+/// A filter in the stack of the parameters, which can be turned off without
+/// losing its settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterStep {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub adjustment: Adjustment,
+    pub enabled: bool,
+}
+
+impl FilterStep {
+    pub fn new(adjustment: Adjustment) -> Self {
+        Self {
+            adjustment,
+            enabled: true,
+        }
+    }
+}
+
+/// Replaces the color of every pixel of an image, passing its channels to `f` as
+/// values between 0 and 1. Images in linear light keep their precision.
+fn map_colors(img: &DynamicImage, f: impl Fn([f32; 3]) -> [f32; 3]) -> DynamicImage {
+    match img {
+        DynamicImage::ImageRgba32F(buffer) => {
+            let mut mapped = buffer.clone();
+            for pixel in mapped.pixels_mut() {
+                let [r, g, b] = f([pixel.0[0], pixel.0[1], pixel.0[2]]);
+                pixel.0[..3].copy_from_slice(&[r, g, b].map(|c| c.clamp(0.0, 1.0)));
+            }
+            DynamicImage::ImageRgba32F(mapped)
+        }
+        _ => {
+            let mut mapped = img.to_rgba8();
+            for pixel in mapped.pixels_mut() {
+                let [r, g, b] = f([pixel.0[0], pixel.0[1], pixel.0[2]].map(|c| c as f32 / 255.0));
+                let quantized = [r, g, b].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                pixel.0[..3].copy_from_slice(&quantized);
+            }
+            DynamicImage::ImageRgba8(mapped)
+        }
+    }
+}
+
+/// Applies `f` to a floating point copy of an image, converting the result back
+/// to the precision of the original one.
+fn map_buffer(img: &DynamicImage, f: impl FnOnce(&Rgba32FImage) -> Rgba32FImage) -> DynamicImage {
+    match img {
+        DynamicImage::ImageRgba32F(buffer) => DynamicImage::ImageRgba32F(f(buffer)),
+        _ => {
+            let mapped = DynamicImage::ImageRgba32F(f(&img.to_rgba32f()));
+            DynamicImage::ImageRgba8(mapped.to_rgba8())
+        }
+    }
+}
+
+/// Bilateral filter with a spatial standard deviation of half the given radius.
+fn bilateral(img: &DynamicImage, radius: f32) -> DynamicImage {
+    // Differences in color larger than this are mostly kept as edges.
+    const RANGE_SIGMA: f32 = 0.1;
+
+    let size = radius.round() as i64;
+    if size < 1 {
+        return img.clone();
+    }
+
+    let offsets = window_offsets(size);
+    let spatial = -0.5 / (radius / 2.0).powi(2);
+    let range = -0.5 / RANGE_SIGMA.powi(2);
+
+    map_buffer(img, |buffer| {
+        Rgba32FImage::from_fn(buffer.width(), buffer.height(), |x, y| {
+            let center = buffer.get_pixel(x, y).0;
+            let mut sum = [0.0; 3];
+            let mut total = 0.0;
+
+            for_each_neighbor(buffer, x, y, &offsets, |pixel, distance| {
+                let difference: f32 = (0..3).map(|c| (pixel[c] - center[c]).powi(2)).sum();
+                let weight = (distance * spatial + difference * range).exp();
+                for c in 0..3 {
+                    sum[c] += pixel[c] * weight;
+                }
+                total += weight;
+            });
+
+            let [r, g, b] = sum.map(|c| c / total);
+            image::Rgba([r, g, b, center[3]])
+        })
+    })
+}
+
+/// Median filter over a square window of the given radius.
+fn median(img: &DynamicImage, radius: f32) -> DynamicImage {
+    let size = radius.round() as i64;
+    if size < 1 {
+        return img.clone();
+    }
+
+    let offsets = window_offsets(size);
+    map_buffer(img, |buffer| {
+        let mut values: [Vec<f32>; 3] = Default::default();
+        Rgba32FImage::from_fn(buffer.width(), buffer.height(), |x, y| {
+            values.iter_mut().for_each(Vec::clear);
+            for_each_neighbor(buffer, x, y, &offsets, |pixel, _| {
+                for (c, values) in values.iter_mut().enumerate() {
+                    values.push(pixel[c]);
+                }
+            });
+
+            let mut pixel = buffer.get_pixel(x, y).0;
+            for (value, values) in pixel[..3].iter_mut().zip(&mut values) {
+                let middle = values.len() / 2;
+                *value = *values.select_nth_unstable_by(middle, f32::total_cmp).1;
+            }
+            image::Rgba(pixel)
+        })
+    })
+}
+
+/// Largest number of pixels sampled on each side of the center of a window.
+const MAX_WINDOW_SAMPLES: i64 = 4;
+
+/// Returns the offsets from the center of a square window of the given radius
+/// that are sampled. Larger windows are sampled sparsely, so that the cost of the
+/// filters does not grow with their radius.
+fn window_offsets(size: i64) -> Vec<i64> {
+    let samples = size.min(MAX_WINDOW_SAMPLES);
+    (-samples..=samples).map(|i| i * size / samples).collect()
+}
+
+/// Calls `f` with the pixels at the given offsets around `(x, y)` that are inside
+/// the image, along with their squared distance from the center.
+fn for_each_neighbor(
+    buffer: &Rgba32FImage,
+    x: u32,
+    y: u32,
+    offsets: &[i64],
+    mut f: impl FnMut(&[f32], f32),
+) {
+    let (width, height) = (buffer.width() as i64, buffer.height() as i64);
+    let data = buffer.as_raw();
+    let (x, y) = (x as i64, y as i64);
+
+    for &dy in offsets {
+        let ny = y + dy;
+        if !(0..height).contains(&ny) {
+            continue;
+        }
+
+        let row = &data[(ny * width * 4) as usize..][..(width * 4) as usize];
+        for &dx in offsets {
+            let nx = x + dx;
+            if (0..width).contains(&nx) {
+                f(&row[(nx * 4) as usize..][..4], (dx * dx + dy * dy) as f32);
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+//! The BlurThing image processing pipeline, shared by the GUI application and
+//! any other tool that needs to produce the exact same BlurHashes.
+
+mod export;
+mod filters;
+mod params;
+mod pipeline;
+mod stages;
+
+pub use export::save_image;
+pub use filters::{
+    Adjustment, Blur, Brightness, Contrast, Filter, FilterStep, HueRotate, Saturation, Smoothing,
+    WhiteBalance,
+};
+pub use params::{format_hex_color, parse_hex_color, Aspect, Crop, Fit, Params, Resampling};
+pub use pipeline::{average_color, decode, fit_dimensions, validate, Pipeline};
+pub use stages::flatten;
+
+/// Default size of the square the source image is downsampled to fit in before
+/// processing.
+pub const IMAGE_DOWNSAMPLE_SIZE: u32 = 128;
+/// Extensions of the image files that can be loaded by the pipeline.
+pub const ALLOWED_EXTENSIONS: [&str; 8] =
+    ["bmp", "gif", "jpg", "jpeg", "png", "tga", "tiff", "webp"];
+/// Extensions of the image files that decoded hashes can be written to.
+pub const EXPORT_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
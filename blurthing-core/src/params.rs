@@ -0,0 +1,291 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use image::imageops::FilterType;
+
+use crate::filters::{Adjustment, FilterStep};
+use crate::IMAGE_DOWNSAMPLE_SIZE;
+
+/// Parameters that control how the source image is processed and encoded.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Params {
+    pub components: (u32, u32),
+    /// Size of the square the source image is downsampled to fit in, and the
+    /// filter used to resample it.
+    pub downsample_size: u32,
+    pub resampling: Resampling,
+    /// Region of the source image that is hashed, or the whole image if `None`.
+    pub crop: Option<Crop>,
+    /// Color that the transparent areas of the source image are composited over.
+    pub matte: [u8; 3],
+    pub rotation: i8,
+    /// Whether the image is mirrored, after being rotated.
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    /// Aspect ratio that the image is fitted to, or the one of the image itself
+    /// if `None`. The bars added by [`Fit::Contain`] use the matte color.
+    pub aspect: Option<Aspect>,
+    pub fit: Fit,
+    /// Filters applied to the image after it is fitted, from first to last.
+    pub filters: Vec<FilterStep>,
+    /// Whether the adjustments are applied in linear light instead of on the
+    /// gamma-encoded values, which keeps the overall brightness when blurring.
+    pub linear: bool,
+    /// Contrast of the decoded image, which does not affect the hash itself.
+    pub punch: f32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            components: (4, 3),
+            downsample_size: IMAGE_DOWNSAMPLE_SIZE,
+            resampling: Resampling::Lanczos3,
+            crop: None,
+            matte: [255, 255, 255],
+            rotation: 0,
+            flip_horizontal: false,
+            flip_vertical: false,
+            aspect: None,
+            fit: Fit::Cover,
+            filters: Adjustment::ALL.map(FilterStep::new).to_vec(),
+            linear: false,
+            punch: 1.0,
+        }
+    }
+}
+
+/// Filter used to downsample the source image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Resampling {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    #[default]
+    Lanczos3,
+}
+
+impl Resampling {
+    pub const ALL: [Self; 5] = [
+        Self::Nearest,
+        Self::Triangle,
+        Self::CatmullRom,
+        Self::Gaussian,
+        Self::Lanczos3,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Nearest => "Nearest",
+            Self::Triangle => "Triangle",
+            Self::CatmullRom => "Catmull-Rom",
+            Self::Gaussian => "Gaussian",
+            Self::Lanczos3 => "Lanczos",
+        }
+    }
+
+    pub(crate) fn filter_type(&self) -> FilterType {
+        match self {
+            Self::Nearest => FilterType::Nearest,
+            Self::Triangle => FilterType::Triangle,
+            Self::CatmullRom => FilterType::CatmullRom,
+            Self::Gaussian => FilterType::Gaussian,
+            Self::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl FromStr for Resampling {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "nearest" => Ok(Self::Nearest),
+            "triangle" => Ok(Self::Triangle),
+            "catmullrom" | "catmull-rom" => Ok(Self::CatmullRom),
+            "gaussian" => Ok(Self::Gaussian),
+            "lanczos3" | "lanczos" => Ok(Self::Lanczos3),
+            _ => anyhow::bail!("unknown resampling filter: {}", s),
+        }
+    }
+}
+
+/// A rectangular region of an image, in fractions of its width and height so that
+/// it does not depend on the resolution of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Crop {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Crop {
+    /// Returns the region spanned by two opposite corners, limited to the bounds
+    /// of the image.
+    pub fn from_corners((x1, y1): (f32, f32), (x2, y2): (f32, f32)) -> Self {
+        let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+        let (y1, y2) = (y1.clamp(0.0, 1.0), y2.clamp(0.0, 1.0));
+
+        Self {
+            x: x1.min(x2),
+            y: y1.min(y2),
+            width: (x1 - x2).abs(),
+            height: (y1 - y2).abs(),
+        }
+    }
+
+    /// Returns the position and size of the region in an image of the given
+    /// dimensions, in pixels. The region is always at least one pixel large.
+    pub fn pixels(&self, (width, height): (u32, u32)) -> (u32, u32, u32, u32) {
+        let scale = |value: f32, size: u32| (value.clamp(0.0, 1.0) * size as f32).round() as u32;
+
+        let x = scale(self.x, width).min(width.saturating_sub(1));
+        let y = scale(self.y, height).min(height.saturating_sub(1));
+        let w = scale(self.width, width).clamp(1, width - x);
+        let h = scale(self.height, height).clamp(1, height - y);
+        (x, y, w, h)
+    }
+}
+
+impl FromStr for Crop {
+    type Err = anyhow::Error;
+
+    /// Parses a region in the `X,Y,WIDTH,HEIGHT` notation, with every value being
+    /// a fraction of the size of the image.
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|values| values.len() == 4 && values.iter().all(|v| (0.0..=1.0).contains(v)))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "invalid crop '{}', expected four fractions X,Y,WIDTH,HEIGHT",
+                    s
+                )
+            })?;
+
+        let crop = Self {
+            x: values[0],
+            y: values[1],
+            width: values[2],
+            height: values[3],
+        };
+
+        if crop.width <= 0.0 || crop.height <= 0.0 {
+            anyhow::bail!("the crop region must not be empty");
+        }
+        // Allow for the rounding of values like `0.7,0,0.3,1` that end on the edge.
+        if crop.x + crop.width > 1.0 + 1e-4 || crop.y + crop.height > 1.0 + 1e-4 {
+            anyhow::bail!("the crop region must be inside the image");
+        }
+
+        Ok(crop)
+    }
+}
+
+/// An aspect ratio, such as 16:9.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aspect {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Aspect {
+    /// Largest ratio between the longest and the shortest side, which bounds the
+    /// size of the canvas that an image is fitted to.
+    pub const MAX_RATIO: u32 = 16;
+
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Returns the dimensions of an image of the given size once fitted to this
+    /// aspect ratio, either by cropping or by adding bars to it.
+    pub fn fit(&self, (width, height): (u32, u32), fit: Fit) -> (u32, u32) {
+        let max = Self::MAX_RATIO as f64;
+        let ratio = (self.width as f64 / self.height.max(1) as f64).clamp(1.0 / max, max);
+        let is_wider = width as f64 / height.max(1) as f64 > ratio;
+
+        let scaled_width = || ((height as f64 * ratio).round() as u32).max(1);
+        let scaled_height = || ((width as f64 / ratio).round() as u32).max(1);
+        match (fit, is_wider) {
+            (Fit::Cover, true) | (Fit::Contain, false) => (scaled_width(), height),
+            (Fit::Cover, false) | (Fit::Contain, true) => (width, scaled_height()),
+        }
+    }
+}
+
+impl FromStr for Aspect {
+    type Err = anyhow::Error;
+
+    /// Parses an aspect ratio in the `WIDTH:HEIGHT` notation.
+    fn from_str(s: &str) -> Result<Self> {
+        let (width, height) = s
+            .split_once(':')
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+            .filter(|&(w, h): &(u32, u32)| w > 0 && h > 0)
+            .ok_or_else(|| {
+                anyhow::anyhow!("invalid aspect ratio '{}', expected WIDTH:HEIGHT", s)
+            })?;
+
+        if width.max(height) as u64 > width.min(height) as u64 * Self::MAX_RATIO as u64 {
+            anyhow::bail!(
+                "aspect ratios must be between 1:{0} and {0}:1",
+                Self::MAX_RATIO
+            );
+        }
+
+        Ok(Self::new(width, height))
+    }
+}
+
+/// How an image is fitted to an aspect ratio, like the `object-fit` CSS property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Fit {
+    /// The image fills the whole area, and the parts that exceed it are cut off.
+    #[default]
+    Cover,
+    /// The whole image is visible, with bars filling the rest of the area.
+    Contain,
+}
+
+impl FromStr for Fit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cover" => Ok(Self::Cover),
+            "contain" => Ok(Self::Contain),
+            _ => anyhow::bail!("unknown fit mode: {}", s),
+        }
+    }
+}
+
+/// Parses a color in the `RRGGBB` hexadecimal notation, with an optional leading
+/// `#`.
+pub fn parse_hex_color(s: &str) -> Result<[u8; 3]> {
+    let digits = s.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("invalid color '{}', expected the RRGGBB format", s.trim());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap_or_default();
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+/// Formats a color in the `#RRGGBB` hexadecimal notation.
+pub fn format_hex_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
@@ -0,0 +1,287 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+
+use crate::params::Params;
+use crate::stages::{premultiplied, DecodeKey, HashKey, Stage, StageCache};
+
+/// Holds a source image and computes BlurHashes from it.
+///
+/// The intermediate results of the last computation are kept, so that changing
+/// a parameter only recomputes the stages that depend on it.
+#[derive(Debug)]
+pub struct Pipeline {
+    source: Arc<DynamicImage>,
+    cache: Mutex<StageCache>,
+}
+
+impl Clone for Pipeline {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            cache: Default::default(),
+        }
+    }
+}
+
+impl Pipeline {
+    /// Creates a new pipeline from the given image.
+    pub fn new(img: DynamicImage) -> Self {
+        // The full resolution image is kept, since the region that is hashed can
+        // be changed at any time and is downsampled after being cropped.
+        Self {
+            source: Arc::new(img),
+            cache: Default::default(),
+        }
+    }
+
+    /// Creates a new pipeline from the image file at the given path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let loaded = image::open(path).map_err(|e| anyhow!(e.to_string().to_lowercase()))?;
+        Ok(Self::new(loaded))
+    }
+
+    /// Creates a new pipeline from an encoded image, guessing its format.
+    pub fn from_memory(bytes: &[u8]) -> Result<Self> {
+        let loaded =
+            image::load_from_memory(bytes).map_err(|e| anyhow!(e.to_string().to_lowercase()))?;
+        Ok(Self::new(loaded))
+    }
+
+    /// Returns the width and height of the original source image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.source.dimensions()
+    }
+
+    /// Returns a copy of the source image that fits in a square of the given size.
+    pub fn thumbnail(&self, size: u32) -> DynamicImage {
+        premultiplied(&self.source, |img| img.thumbnail(size, size))
+    }
+
+    /// Returns the dimensions of the processed image at the original resolution,
+    /// which are those of the cropped region, swapped when the image is rotated by
+    /// an odd number of steps and then fitted to the target aspect ratio.
+    pub fn output_dimensions(&self, params: &Params) -> (u32, u32) {
+        let (width, height) = self.region_dimensions(params);
+        let rotated = if params.rotation % 2 != 0 {
+            (height, width)
+        } else {
+            (width, height)
+        };
+
+        match params.aspect {
+            Some(aspect) => aspect.fit(rotated, params.fit),
+            None => rotated,
+        }
+    }
+
+    /// Applies the image manipulations described by `params` to the source image.
+    pub fn process(&self, params: &Params) -> DynamicImage {
+        let stages = self.stages(params);
+        self.cache().process(&self.source, &stages).as_ref().clone()
+    }
+
+    /// Computes the BlurHash of the source image with the given parameters.
+    pub fn encode(&self, params: &Params) -> Result<String> {
+        let key = HashKey {
+            stages: self.stages(params),
+            components: params.components,
+        };
+
+        let mut cache = self.cache();
+        if let Some(hash) = cache.hash(&key) {
+            return Ok(hash);
+        }
+
+        let processed = cache.process(&self.source, &key.stages);
+        let (width, height) = processed.dimensions();
+        let buffer = processed.to_rgba8().into_raw();
+
+        let (x, y) = params.components;
+        let hash = blurhash::encode(x, y, width, height, &buffer)
+            .map_err(|_| anyhow!("failed to compute the blurhash"))?;
+
+        cache.set_hash(key, hash.clone());
+        Ok(hash)
+    }
+
+    /// Computes the BlurHash of the source image and decodes it to an image with
+    /// the same proportions, that fits in a square of the given size.
+    pub fn compute(&self, params: &Params, size: u32) -> Result<(String, DynamicImage)> {
+        let key = DecodeKey {
+            hash: self.encode(params)?,
+            dimensions: fit_dimensions(self.output_dimensions(params), size),
+            punch: params.punch,
+        };
+
+        let decoded = match self.cache().decoded(&key) {
+            Some(decoded) => decoded,
+            None => {
+                let (width, height) = key.dimensions;
+                let decoded = Arc::new(decode(&key.hash, width, height, key.punch)?);
+                self.cache().set_decoded(key.clone(), decoded.clone());
+                decoded
+            }
+        };
+
+        Ok((key.hash, decoded.as_ref().clone()))
+    }
+
+    /// Returns the dimensions of the region of the source image that is hashed.
+    fn region_dimensions(&self, params: &Params) -> (u32, u32) {
+        match params.crop {
+            Some(crop) => {
+                let (_, _, width, height) = crop.pixels(self.dimensions());
+                (width, height)
+            }
+            None => self.dimensions(),
+        }
+    }
+
+    fn stages(&self, params: &Params) -> Vec<Stage> {
+        let (width, height) = self.region_dimensions(params);
+        let scale = params.downsample_size as f32 / width.max(height).max(1) as f32;
+        Stage::from_params(params, scale)
+    }
+
+    fn cache(&self) -> MutexGuard<'_, StageCache> {
+        // The cache is always left in a consistent state, even if a computation
+        // panicked while holding the lock.
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Scales the given dimensions to the largest ones that fit in a square of the
+/// given size, keeping their aspect ratio.
+pub fn fit_dimensions((width, height): (u32, u32), size: u32) -> (u32, u32) {
+    let scale = size as f64 / width.max(height).max(1) as f64;
+    let scaled = |value: u32| ((value as f64 * scale).round() as u32).clamp(1, size);
+    (scaled(width), scaled(height))
+}
+
+/// Decodes a BlurHash to an image of the given dimensions.
+pub fn decode(hash: &str, width: u32, height: u32, punch: f32) -> Result<DynamicImage> {
+    validate(hash)?;
+
+    let decoded = blurhash::decode(hash, width, height, punch)
+        .map_err(|_| anyhow!("failed to decode the blurhash"))?;
+    let img = RgbaImage::from_vec(width, height, decoded)
+        .ok_or_else(|| anyhow!("failed to create image from decoded buffer"))?;
+
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
+/// Checks that a string is a well-formed BlurHash, describing the first problem
+/// found in a way that can be shown to the user.
+pub fn validate(hash: &str) -> Result<()> {
+    if let Some((i, c)) = hash
+        .char_indices()
+        .find(|&(_, c)| !c.is_ascii() || !BASE83_CHARACTERS.contains(&(c as u8)))
+    {
+        anyhow::bail!("invalid character '{}' at position {}", c, i + 1);
+    }
+
+    if hash.len() < 6 {
+        anyhow::bail!("a blurhash must be at least 6 characters long");
+    }
+
+    let size_flag = base83_decode(&hash[..1])?;
+    let (x, y) = (size_flag % 9 + 1, size_flag / 9 + 1);
+    let expected = 4 + 2 * (x * y) as usize;
+    if hash.len() != expected {
+        anyhow::bail!(
+            "a blurhash with {}x{} components must be {} characters long, found {}",
+            x,
+            y,
+            expected,
+            hash.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the average color of the image a BlurHash was computed from, as
+/// stored in its DC component.
+pub fn average_color(hash: &str) -> Result<[u8; 3]> {
+    validate(hash)?;
+
+    // Without punch the AC components vanish, leaving only the DC color.
+    let decoded =
+        blurhash::decode(hash, 1, 1, 0.0).map_err(|_| anyhow!("failed to decode the blurhash"))?;
+    Ok([decoded[0], decoded[1], decoded[2]])
+}
+
+const BASE83_CHARACTERS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn base83_decode(s: &str) -> Result<u32> {
+    s.bytes().try_fold(0, |value, c| {
+        let digit = BASE83_CHARACTERS
+            .iter()
+            .position(|&x| x == c)
+            .ok_or_else(|| anyhow!("invalid character in the blurhash: {}", c as char))?;
+        Ok(value * 83 + digit as u32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hashes_of_every_size() {
+        let img = RgbaImage::from_fn(32, 24, |x, y| {
+            image::Rgba([x as u8 * 8, y as u8 * 10, 90, 255])
+        });
+        let flat = RgbaImage::from_pixel(32, 24, image::Rgba([200, 120, 40, 255]));
+
+        for x in 1..=9 {
+            for y in 1..=9 {
+                let hash = blurhash::encode(x, y, 32, 24, img.as_raw()).unwrap();
+                validate(&hash).unwrap();
+                decode(&hash, 8, 8, 1.0).unwrap();
+
+                let hash = blurhash::encode(x, y, 32, 24, flat.as_raw()).unwrap();
+                let color = average_color(&hash).unwrap();
+                let expected = [200, 120, 40];
+                // The encoder can round the average of a flat image off by one.
+                assert!(color.iter().zip(expected).all(|(&c, e)| c.abs_diff(e) <= 1));
+            }
+        }
+    }
+
+    #[test]
+    fn ignores_the_color_of_transparent_pixels() {
+        // Checkerboards of opaque and transparent squares, which only differ in
+        // the color hidden in their transparent pixels.
+        let checkerboard = |hidden: [u8; 3]| {
+            let [r, g, b] = hidden;
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(1000, 1000, |x, y| {
+                if (x / 50 + y / 50) % 2 == 0 {
+                    image::Rgba([40, 90, 160, 255])
+                } else {
+                    image::Rgba([r, g, b, 0])
+                }
+            }))
+        };
+
+        let params = Params {
+            matte: [230, 200, 20],
+            ..Params::default()
+        };
+        let black = Pipeline::new(checkerboard([0, 0, 0]));
+        let red = Pipeline::new(checkerboard([255, 0, 0]));
+        assert_eq!(black.encode(&params).unwrap(), red.encode(&params).unwrap());
+        assert_eq!(black.process(&params), red.process(&params));
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        assert!(validate("LeKn#k").is_err());
+        assert!(validate("LeKn#kjF~pozkDjsjsa}~Vj]ozj\\").is_err());
+        assert!(validate("LeKn#kjF~pozkDjsjsa}~Vj]ozjsa").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use image::imageops;
+use image::{DynamicImage, GenericImageView, Rgba, Rgba32FImage, RgbaImage};
+
+use crate::filters::{Adjustment, Filter};
+use crate::params::{Aspect, Crop, Fit, Params, Resampling};
+
+/// A single image manipulation, holding the parameters it depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Stage {
+    Downsample(Option<Crop>, u32, Resampling),
+    Matte([u8; 3]),
+    Rotate(i8),
+    Flip(bool, bool),
+    Fit(Aspect, Fit, [u8; 3]),
+    ToLinear,
+    ToSrgb,
+    /// A filter, along with the size of the image it is applied to relative to
+    /// the source one.
+    Filter(Adjustment, f32),
+}
+
+impl Stage {
+    /// Returns the stages described by `params`, in the order they are applied.
+    /// The `scale` of the downsampled image is used by the filters whose settings
+    /// are measured on the source image.
+    pub fn from_params(params: &Params, scale: f32) -> Vec<Self> {
+        let mut stages = vec![
+            Self::Downsample(params.crop, params.downsample_size, params.resampling),
+            Self::Matte(params.matte),
+            Self::Rotate(params.rotation),
+            Self::Flip(params.flip_horizontal, params.flip_vertical),
+        ];
+        if let Some(aspect) = params.aspect {
+            stages.push(Self::Fit(aspect, params.fit, params.matte));
+        }
+        if params.linear {
+            stages.push(Self::ToLinear);
+        }
+
+        stages.extend(
+            (params.filters.iter())
+                .filter(|step| step.enabled)
+                .map(|step| Self::Filter(step.adjustment, scale)),
+        );
+
+        if params.linear {
+            stages.push(Self::ToSrgb);
+        }
+
+        stages
+    }
+
+    pub fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        match *self {
+            Self::Downsample(crop, size, resampling) => downsample(img, crop, size, resampling),
+            Self::Matte(matte) => flatten(img, matte),
+            Self::Rotate(1) => img.rotate90(),
+            Self::Rotate(2) => img.rotate180(),
+            Self::Rotate(3) => img.rotate270(),
+            Self::Rotate(_) => img.clone(),
+            Self::Flip(false, false) => img.clone(),
+            Self::Flip(horizontal, vertical) => {
+                let mut flipped = img.clone();
+                if horizontal {
+                    flipped = flipped.fliph();
+                }
+                if vertical {
+                    flipped = flipped.flipv();
+                }
+                flipped
+            }
+            Self::Fit(aspect, fit, matte) => fit_to_aspect(img, aspect, fit, matte),
+            Self::ToLinear => to_linear(img),
+            Self::ToSrgb => to_srgb(img),
+            Self::Filter(adjustment, scale) => adjustment.apply(img, scale),
+        }
+    }
+}
+
+/// Downsamples the source image to a smaller size for faster processing, keeping
+/// its aspect ratio so that the components are sampled without distortion. The
+/// image is cropped first, so that the region keeps as much detail as possible.
+fn downsample(
+    img: &DynamicImage,
+    crop: Option<Crop>,
+    size: u32,
+    resampling: Resampling,
+) -> DynamicImage {
+    let size = size.max(1);
+    let filter = resampling.filter_type();
+    match crop {
+        Some(crop) => {
+            let (x, y, width, height) = crop.pixels(img.dimensions());
+            let cropped = img.crop_imm(x, y, width, height);
+            premultiplied(&cropped, |img| img.resize(size, size, filter))
+        }
+        None => premultiplied(img, |img| img.resize(size, size, filter)),
+    }
+}
+
+/// Applies a resampling `f` to an image with its colors premultiplied by their
+/// alpha, so that the colors hidden in the transparent pixels are not blended
+/// into the visible ones.
+pub(crate) fn premultiplied(
+    img: &DynamicImage,
+    f: impl FnOnce(&DynamicImage) -> DynamicImage,
+) -> DynamicImage {
+    if !img.color().has_alpha() {
+        return f(img);
+    }
+
+    let mut buffer = img.to_rgba8();
+    for pixel in buffer.pixels_mut() {
+        let alpha = pixel.0[3] as u32;
+        for c in &mut pixel.0[..3] {
+            *c = ((*c as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+
+    let mut resampled = f(&DynamicImage::ImageRgba8(buffer)).to_rgba8();
+    for pixel in resampled.pixels_mut() {
+        let alpha = pixel.0[3] as u32;
+        for c in &mut pixel.0[..3] {
+            *c = match alpha {
+                0 => 0,
+                _ => ((*c as u32 * 255 + alpha / 2) / alpha).min(255) as u8,
+            };
+        }
+    }
+
+    DynamicImage::ImageRgba8(resampled)
+}
+
+/// Fits an image to the given aspect ratio, keeping it centred.
+fn fit_to_aspect(img: &DynamicImage, aspect: Aspect, fit: Fit, matte: [u8; 3]) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let (fit_width, fit_height) = aspect.fit((width, height), fit);
+
+    match fit {
+        Fit::Cover => img.crop_imm(
+            (width - fit_width) / 2,
+            (height - fit_height) / 2,
+            fit_width,
+            fit_height,
+        ),
+        Fit::Contain => {
+            let [r, g, b] = matte;
+            let mut canvas = RgbaImage::from_pixel(fit_width, fit_height, Rgba([r, g, b, 255]));
+            let x = (fit_width - width) / 2;
+            let y = (fit_height - height) / 2;
+            imageops::overlay(&mut canvas, &img.to_rgba8(), x as i64, y as i64);
+            DynamicImage::ImageRgba8(canvas)
+        }
+    }
+}
+
+/// Composites an image over a solid color, so that the color of the transparent
+/// pixels does not leak into the hash.
+pub fn flatten(img: &DynamicImage, matte: [u8; 3]) -> DynamicImage {
+    if !img.color().has_alpha() {
+        return img.clone();
+    }
+
+    let mut buffer: RgbaImage = img.to_rgba8();
+    for pixel in buffer.pixels_mut() {
+        let alpha = pixel.0[3] as u32;
+        for (c, m) in pixel.0[..3].iter_mut().zip(matte) {
+            *c = ((*c as u32 * alpha + m as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+        pixel.0[3] = 255;
+    }
+
+    DynamicImage::ImageRgba8(buffer)
+}
+
+/// Converts the color channels of an image from sRGB to linear light, storing
+/// them as floating point values to avoid banding in the dark tones.
+fn to_linear(img: &DynamicImage) -> DynamicImage {
+    let mut buffer: Rgba32FImage = img.to_rgba32f();
+    for pixel in buffer.pixels_mut() {
+        for c in &mut pixel.0[..3] {
+            *c = if *c <= 0.04045 {
+                *c / 12.92
+            } else {
+                ((*c + 0.055) / 1.055).powf(2.4)
+            };
+        }
+    }
+
+    DynamicImage::ImageRgba32F(buffer)
+}
+
+/// Converts the color channels of an image from linear light back to sRGB.
+fn to_srgb(img: &DynamicImage) -> DynamicImage {
+    let mut buffer: Rgba32FImage = img.to_rgba32f();
+    for pixel in buffer.pixels_mut() {
+        for c in &mut pixel.0[..3] {
+            let linear = c.clamp(0.0, 1.0);
+            *c = if linear <= 0.0031308 {
+                linear * 12.92
+            } else {
+                1.055 * linear.powf(1.0 / 2.4) - 0.055
+            };
+        }
+    }
+
+    DynamicImage::ImageRgba8(DynamicImage::ImageRgba32F(buffer).to_rgba8())
+}
+
+/// Inputs of the encoding stage.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HashKey {
+    pub stages: Vec<Stage>,
+    pub components: (u32, u32),
+}
+
+/// Inputs of the decoding stage.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DecodeKey {
+    pub hash: String,
+    pub dimensions: (u32, u32),
+    pub punch: f32,
+}
+
+/// Results of the pipeline for the last computed parameters. Every result is
+/// stored along with all the inputs it depends on, so that changing a parameter
+/// only recomputes the stages that come after it.
+#[derive(Debug, Default)]
+pub(crate) struct StageCache {
+    /// Output of each of the stages in `stages`, which are a prefix of the last
+    /// requested ones.
+    stages: Vec<Stage>,
+    images: Vec<Arc<DynamicImage>>,
+
+    hash: Option<(HashKey, String)>,
+    decoded: Option<(DecodeKey, Arc<DynamicImage>)>,
+}
+
+impl StageCache {
+    /// Applies the given stages to `source`, reusing the longest prefix of them
+    /// that was already computed.
+    pub fn process(&mut self, source: &DynamicImage, stages: &[Stage]) -> Arc<DynamicImage> {
+        let reused = self
+            .stages
+            .iter()
+            .zip(stages)
+            .take_while(|(cached, stage)| cached == stage)
+            .count();
+        self.stages.truncate(reused);
+        self.images.truncate(reused);
+
+        for stage in &stages[reused..] {
+            let input = self.images.last().map_or(source, |img| img.as_ref());
+            let output = Arc::new(stage.apply(input));
+            self.stages.push(stage.clone());
+            self.images.push(output);
+        }
+
+        match self.images.last() {
+            Some(img) => img.clone(),
+            None => Arc::new(source.clone()),
+        }
+    }
+
+    pub fn hash(&self, key: &HashKey) -> Option<String> {
+        self.hash
+            .as_ref()
+            .filter(|(k, _)| k == key)
+            .map(|(_, hash)| hash.clone())
+    }
+
+    pub fn set_hash(&mut self, key: HashKey, hash: String) {
+        self.hash = Some((key, hash));
+    }
+
+    pub fn decoded(&self, key: &DecodeKey) -> Option<Arc<DynamicImage>> {
+        self.decoded
+            .as_ref()
+            .filter(|(k, _)| k == key)
+            .map(|(_, img)| img.clone())
+    }
+
+    pub fn set_decoded(&mut self, key: DecodeKey, img: Arc<DynamicImage>) {
+        self.decoded = Some((key, img));
+    }
+}
@@ -1,15 +1,23 @@
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use ::image::imageops::FilterType;
-use ::image::{DynamicImage, GenericImageView, RgbaImage};
+use ::image::DynamicImage;
 use anyhow::{anyhow, Result};
+use blurthing_core::{
+    format_hex_color, Adjustment, Aspect, Blur, Brightness, Contrast, Crop, Filter, FilterStep,
+    Fit, HueRotate, Pipeline, Resampling, Saturation, Smoothing, WhiteBalance, ALLOWED_EXTENSIONS,
+    EXPORT_EXTENSIONS,
+};
 use iced::alignment::{Horizontal, Vertical};
 use iced::widget::{Image, Space};
 use iced::{Application, Command, Event, Length, Subscription};
 use native_dialog::{FileDialog, MessageDialog, MessageType};
 
+use crate::export::ExportOptions;
 use crate::message::{Interaction, Message};
+use crate::preferences::Preferences;
 use crate::state::State;
 use crate::styles;
 use crate::undo_history::UndoHistory;
@@ -17,18 +25,55 @@ use crate::utils;
 use crate::widgets::*;
 
 pub const PREVIEW_SIZE: u32 = 512;
-const IMAGE_DOWNSAMPLE_SIZE: u32 = 128;
-const ALLOWED_EXTENSIONS: [&str; 8] = ["bmp", "gif", "jpg", "jpeg", "png", "tga", "tiff", "webp"];
-const EXPORT_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+/// Aspect ratios that can be picked in the controls, besides the source one.
+const ASPECT_PRESETS: [Aspect; 5] = [
+    Aspect::new(1, 1),
+    Aspect::new(4, 3),
+    Aspect::new(3, 2),
+    Aspect::new(16, 9),
+    Aspect::new(9, 16),
+];
+/// Sizes the source image can be downsampled to before being hashed.
+const DOWNSAMPLE_PRESETS: [u32; 5] = [32, 64, 128, 256, 512];
 
 pub struct BlurThing {
-    img: Option<DynamicImage>,
+    pipeline: Option<Arc<Pipeline>>,
+    source_path: Option<PathBuf>,
+    thumbnail: Option<DynamicImage>,
     computed: Option<(String, DynamicImage)>,
+    hash_input: String,
+    hash_error: Option<String>,
+    matte_input: String,
 
     state: State,
     history: UndoHistory<State>,
 
+    export_options: ExportOptions,
+    is_export_options_shown: bool,
+    is_exporting: bool,
+
+    // The crop region is dragged over a thumbnail of the source image, with the
+    // cursor position stored in fractions of its size.
+    is_editing_crop: bool,
+    crop_cursor: (f32, f32),
+    crop_anchor: Option<(f32, f32)>,
+
+    preferences: Preferences,
+
     is_downloading_image: bool,
+
+    // Identifier of the latest requested image load, shared with the background
+    // tasks so that a superseded load can stop before creating the pipeline and
+    // the thumbnail of the image.
+    load_id: Arc<AtomicU64>,
+    loading_file: Option<String>,
+    loading_path: Option<PathBuf>,
+
+    // Identifier of the latest requested computation, used to discard the results
+    // that were superseded while they were being computed in the background.
+    computation_id: u64,
+    is_computing: bool,
+    is_computation_pending: bool,
 }
 
 impl Application for BlurThing {
@@ -39,13 +84,36 @@ impl Application for BlurThing {
 
     fn new(_flags: ()) -> (Self, Command<Self::Message>) {
         let mut instance = Self {
-            img: None,
+            pipeline: None,
+            source_path: None,
+            thumbnail: None,
             computed: None,
+            hash_input: String::new(),
+            hash_error: None,
+            matte_input: String::new(),
 
             state: Default::default(),
             history: UndoHistory::new(),
 
+            export_options: Default::default(),
+            is_export_options_shown: false,
+            is_exporting: false,
+
+            is_editing_crop: false,
+            crop_cursor: (0.0, 0.0),
+            crop_anchor: None,
+
+            preferences: Preferences::load(),
+
             is_downloading_image: false,
+
+            load_id: Default::default(),
+            loading_file: None,
+            loading_path: None,
+
+            computation_id: 0,
+            is_computing: false,
+            is_computation_pending: false,
         };
 
         instance.reset_settings();
@@ -69,14 +137,7 @@ impl Application for BlurThing {
                     .to_lowercase();
 
                 if ALLOWED_EXTENSIONS.contains(&extension.as_str()) {
-                    if let Err(e) = self.load_image_file(path) {
-                        eprintln!("image load failed: {}", e);
-                        _ = MessageDialog::new()
-                            .set_type(MessageType::Error)
-                            .set_title("Image Load Error")
-                            .set_text(&format!("failed to load image: {}", e))
-                            .show_alert();
-                    }
+                    return self.load_image_file(path);
                 } else {
                     eprintln!("received unsupported file type: {}", extension);
                     _ = MessageDialog::new()
@@ -90,7 +151,7 @@ impl Application for BlurThing {
                 self.is_downloading_image = false;
 
                 match result {
-                    Ok(img) => self.load_image(img),
+                    Ok(img) => return self.load_image(img),
                     Err(e) => {
                         eprintln!("failed to download the image: {}", e);
                         _ = MessageDialog::new()
@@ -101,6 +162,68 @@ impl Application for BlurThing {
                     }
                 }
             }
+            Message::ImageLoaded(id, result) => {
+                // Results of loads that were replaced by a newer one are discarded.
+                if id != self.load_id.load(Ordering::SeqCst) {
+                    return Command::none();
+                }
+
+                self.loading_file = None;
+                match result {
+                    Ok((pipeline, thumbnail)) => {
+                        self.source_path = self.loading_path.take();
+                        self.thumbnail = Some(thumbnail);
+                        return self.load_pipeline(pipeline);
+                    }
+                    Err(e) => {
+                        self.loading_path = None;
+                        eprintln!("image load failed: {}", e);
+                        _ = MessageDialog::new()
+                            .set_type(MessageType::Error)
+                            .set_title("Image Load Error")
+                            .set_text(&format!("failed to load image: {}", e))
+                            .show_alert();
+                    }
+                }
+            }
+            Message::BlurHashComputed(id, result) => {
+                self.is_computing = false;
+
+                // Parameters changed while computing, so this result is already
+                // outdated and the latest ones are computed instead.
+                if self.is_computation_pending {
+                    return self.compute_and_apply_blurhash();
+                }
+
+                if id == self.computation_id {
+                    match result {
+                        Ok(computed) => self.apply_computed(computed),
+                        Err(e) => {
+                            eprintln!("failed to compute blurhash: {}", e);
+                            _ = MessageDialog::new()
+                                .set_type(MessageType::Error)
+                                .set_title("Computation Error")
+                                .set_text(&format!("failed to compute blurhash: {}", e))
+                                .show_alert();
+                        }
+                    }
+                }
+            }
+            Message::ImageExported(result) => {
+                self.is_exporting = false;
+
+                match result {
+                    Ok(()) => self.is_export_options_shown = false,
+                    Err(e) => {
+                        eprintln!("image export failed: {}", e);
+                        _ = MessageDialog::new()
+                            .set_type(MessageType::Error)
+                            .set_title("Image Export Error")
+                            .set_text(&format!("failed to export image: {}", e))
+                            .show_alert();
+                    }
+                }
+            }
         }
 
         Command::none()
@@ -109,11 +232,15 @@ impl Application for BlurThing {
     fn subscription(&self) -> Subscription<Self::Message> {
         iced::event::listen_with(|event, _| {
             match event {
-                Event::Window(_, event) => match event {
-                    // Handle file drops in the application window.
-                    iced::window::Event::FileDropped(file) => Some(Message::FileDropped(file)),
-                    _ => None,
-                },
+                // Handle file drops in the application window.
+                Event::Window(_, iced::window::Event::FileDropped(file)) => {
+                    Some(Message::FileDropped(file))
+                }
+                // Crop selections end wherever the button is released, even when
+                // the cursor has left the preview.
+                Event::Mouse(iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left)) => {
+                    Some(Message::Interaction(Interaction::EndCrop))
+                }
                 Event::Keyboard(event) => match event {
                     // Handle application hotkeys (when the command / control key is pressed).
                     iced::keyboard::Event::KeyPressed { key, modifiers, .. }
@@ -128,10 +255,16 @@ impl Application for BlurThing {
         })
     }
 
-    fn view(&self) -> Element<Self::Message> {
+    fn view(&self) -> Element<'_, Self::Message> {
+        let panel = if self.is_export_options_shown {
+            self.export_options()
+        } else {
+            self.controls()
+        };
+
         let right = Column::new()
             .push(Container::new(self.header()).width(Length::Fill))
-            .push(Scrollable::new(self.controls()).height(Length::Fill))
+            .push(Scrollable::new(panel).height(Length::Fill))
             .push(Container::new(self.footer()));
 
         let content: Element<Interaction> = Row::new().push(self.preview()).push(right).into();
@@ -149,13 +282,17 @@ impl BlurThing {
             Interaction::Undo => {
                 if let Some(state) = self.history.undo() {
                     self.state = state.clone();
-                    self.compute_and_apply_blurhash();
+                    self.matte_input = format_hex_color(self.state.matte);
+                    self.remember_preferences();
+                    return self.request_computation();
                 }
             }
             Interaction::Redo => {
                 if let Some(state) = self.history.redo() {
                     self.state = state.clone();
-                    self.compute_and_apply_blurhash();
+                    self.matte_input = format_hex_color(self.state.matte);
+                    self.remember_preferences();
+                    return self.request_computation();
                 }
             }
 
@@ -164,20 +301,37 @@ impl BlurThing {
                     .add_filter("Image File", &ALLOWED_EXTENSIONS)
                     .show_open_single_file()
                 {
-                    if let Err(e) = self.load_image_file(path) {
-                        eprintln!("image load failed: {}", e);
-                        _ = MessageDialog::new()
-                            .set_type(MessageType::Error)
-                            .set_title("Image Load Error")
-                            .set_text(&format!("failed to load image: {}", e))
-                            .show_alert();
-                    }
+                    return self.load_image_file(path);
                 }
             }
+            Interaction::ShowExportOptions => {
+                if self.computed.is_some() {
+                    self.is_export_options_shown = true;
+                }
+            }
+            Interaction::HideExportOptions => {
+                self.is_export_options_shown = false;
+            }
             Interaction::ExportImage => {
-                if self.img.is_none() {
+                let Some((hash, _)) = &self.computed else {
                     return Command::none();
-                }
+                };
+
+                let source = self
+                    .pipeline
+                    .as_ref()
+                    .map(|p| p.output_dimensions(&self.state));
+                let (width, height) = match self.export_options.dimensions(source) {
+                    Ok(dimensions) => dimensions,
+                    Err(e) => {
+                        _ = MessageDialog::new()
+                            .set_type(MessageType::Warning)
+                            .set_title("Invalid Export Size")
+                            .set_text(&e.to_string())
+                            .show_alert();
+                        return Command::none();
+                    }
+                };
 
                 let timestamp = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -189,38 +343,59 @@ impl BlurThing {
                     .set_filename(&format!("blurthing-{}.jpg", timestamp))
                     .show_save_single_file()
                 {
-                    // Compute a new high-resolution image with the current parameters.
-                    match self.compute_blurhash(4196) {
-                        Ok((_, img)) => {
-                            if let Err(e) = img.clone().into_rgb8().save(path) {
-                                eprintln!("image export failed: {}", e);
-                                _ = MessageDialog::new()
-                                    .set_type(MessageType::Error)
-                                    .set_title("Image Export Error")
-                                    .set_text(&format!(
-                                        "failed to export image: {}",
-                                        e.to_string().to_lowercase()
-                                    ))
-                                    .show_alert();
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("failed to compute blurhash to export: {}", e);
-                            _ = MessageDialog::new()
-                                .set_type(MessageType::Error)
-                                .set_title("Computation Error")
-                                .set_text(&format!("failed to compute blurhash: {}", e))
-                                .show_alert();
-                        }
-                    }
+                    let hash = hash.clone();
+                    let punch = self.state.punch;
+                    let quality = self.export_options.quality;
+                    self.is_exporting = true;
+
+                    // Decode a new image from the current hash, at the requested size,
+                    // in the background since large images take a while.
+                    return Command::perform(
+                        async move {
+                            let img = blurthing_core::decode(&hash, width, height, punch)
+                                .map_err(|e| anyhow!("failed to decode blurhash: {}", e))?;
+                            blurthing_core::save_image(&img, &path, quality)
+                        },
+                        Message::ImageExported,
+                    );
                 }
             }
+            Interaction::ToggleMatchSource(match_source) => {
+                self.export_options.match_source = match_source;
+            }
+            Interaction::UpExportWidth(width) => {
+                self.export_options.width = width;
+            }
+            Interaction::UpExportHeight(height) => {
+                self.export_options.height = height;
+            }
+            Interaction::UpExportQuality(quality) => {
+                self.export_options.quality = quality;
+            }
 
             Interaction::CopyToClipboard => {
                 if let Some((hash, _)) = &self.computed {
                     return iced::clipboard::write(hash.clone());
                 }
             }
+            Interaction::UpHash(hash) => {
+                self.hash_input = hash;
+                self.hash_error = None;
+                self.computed = None;
+                // Discard any computation in progress, as it would replace the hash.
+                self.computation_id += 1;
+                self.is_computation_pending = false;
+
+                // Decode the typed hash for the preview. It is replaced again by the
+                // hash of the source image as soon as a parameter is changed.
+                let hash = self.hash_input.trim();
+                if !hash.is_empty() {
+                    match self.decode_preview(hash) {
+                        Ok(img) => self.computed = Some((hash.to_string(), img)),
+                        Err(e) => self.hash_error = Some(e.to_string()),
+                    }
+                }
+            }
             Interaction::ImFeelingLucky => {
                 self.is_downloading_image = true;
                 return Command::perform(async move { utils::download_random_image() }, |result| {
@@ -244,40 +419,152 @@ impl BlurThing {
             }
             Interaction::UpX(x) => {
                 self.state.components.0 = x;
-                self.compute_and_apply_blurhash();
+                return self.request_computation();
             }
             Interaction::UpY(y) => {
                 self.state.components.1 = y;
-                self.compute_and_apply_blurhash();
+                return self.request_computation();
+            }
+            Interaction::UpDownsampleSize(size) => {
+                self.state.downsample_size = size;
+                self.history.push(self.state.clone());
+                self.remember_preferences();
+                return self.request_computation();
+            }
+            Interaction::UpResampling(resampling) => {
+                self.state.resampling = resampling;
+                self.history.push(self.state.clone());
+                self.remember_preferences();
+                return self.request_computation();
+            }
+            Interaction::UpMatte(input) => {
+                // The color is only applied once the input is a complete one, so
+                // that each of them is a single step in the history.
+                let parsed = blurthing_core::parse_hex_color(&input).ok();
+                self.matte_input = input;
+                if let Some(matte) = parsed.filter(|&matte| matte != self.state.matte) {
+                    self.state.matte = matte;
+                    self.history.push(self.state.clone());
+                    self.remember_preferences();
+                    return self.request_computation();
+                }
+            }
+            Interaction::EditCrop => {
+                self.is_editing_crop = !self.is_editing_crop && self.thumbnail.is_some();
+                self.crop_anchor = None;
+            }
+            Interaction::ResetCrop => {
+                self.state.crop = None;
+                self.history.push(self.state.clone());
+                return self.request_computation();
+            }
+            Interaction::MoveCropCursor(position) => {
+                if let Some(thumbnail) = &self.thumbnail {
+                    self.crop_cursor = (
+                        position.x / thumbnail.width() as f32,
+                        position.y / thumbnail.height() as f32,
+                    );
+                    if let Some(anchor) = self.crop_anchor {
+                        self.state.crop = Some(Crop::from_corners(anchor, self.crop_cursor));
+                    }
+                }
+            }
+            Interaction::StartCrop => {
+                self.crop_anchor = Some(self.crop_cursor);
+            }
+            Interaction::EndCrop => {
+                if self.crop_anchor.take().is_some() {
+                    // A click without dragging selects the whole image again.
+                    if let Some(crop) = self.state.crop {
+                        if crop.width < 0.01 || crop.height < 0.01 {
+                            self.state.crop = None;
+                        }
+                    }
+
+                    self.history.push(self.state.clone());
+                    return self.request_computation();
+                }
             }
             Interaction::RotateCW => {
                 self.state.rotation = (self.state.rotation + 1) % 4;
-                self.compute_and_apply_blurhash();
                 self.history.push(self.state.clone());
+                return self.request_computation();
             }
             Interaction::RotateCCW => {
                 self.state.rotation = (self.state.rotation + 3) % 4;
-                self.compute_and_apply_blurhash();
                 self.history.push(self.state.clone());
+                return self.request_computation();
             }
-            Interaction::UpBlur(blur) => {
-                self.state.blur = blur;
-                self.compute_and_apply_blurhash();
+            Interaction::FlipHorizontal => {
+                self.state.flip_horizontal = !self.state.flip_horizontal;
+                self.history.push(self.state.clone());
+                return self.request_computation();
             }
-            Interaction::UpHue(hue) => {
-                self.state.hue_rotate = hue;
-                self.compute_and_apply_blurhash();
+            Interaction::FlipVertical => {
+                self.state.flip_vertical = !self.state.flip_vertical;
+                self.history.push(self.state.clone());
+                return self.request_computation();
             }
-            Interaction::UpBrightness(brightness) => {
-                self.state.brightness = brightness;
-                self.compute_and_apply_blurhash();
+            Interaction::UpAspect(aspect) => {
+                self.state.aspect = aspect;
+                self.history.push(self.state.clone());
+                return self.request_computation();
             }
-            Interaction::UpContrast(contrast) => {
-                self.state.contrast = contrast;
-                self.compute_and_apply_blurhash();
+            Interaction::UpFit(fit) => {
+                self.state.fit = fit;
+                self.history.push(self.state.clone());
+                return self.request_computation();
+            }
+            Interaction::UpFilter(index, adjustment) => {
+                if let Some(step) = self.state.filters.get_mut(index) {
+                    step.adjustment = adjustment;
+                    return self.request_computation();
+                }
+            }
+            Interaction::SetFilter(index, adjustment) => {
+                if let Some(step) = self.state.filters.get_mut(index) {
+                    step.adjustment = adjustment;
+                    self.history.push(self.state.clone());
+                    return self.request_computation();
+                }
+            }
+            Interaction::ToggleFilter(index, enabled) => {
+                if let Some(step) = self.state.filters.get_mut(index) {
+                    step.enabled = enabled;
+                    self.history.push(self.state.clone());
+                    return self.request_computation();
+                }
+            }
+            Interaction::MoveFilter(from, to) => {
+                let len = self.state.filters.len();
+                if from < len && to < len {
+                    let step = self.state.filters.remove(from);
+                    self.state.filters.insert(to, step);
+                    self.history.push(self.state.clone());
+                    return self.request_computation();
+                }
+            }
+            Interaction::RemoveFilter(index) => {
+                if index < self.state.filters.len() {
+                    self.state.filters.remove(index);
+                    self.history.push(self.state.clone());
+                    return self.request_computation();
+                }
+            }
+            Interaction::AddFilter(adjustment) => {
+                self.state.filters.push(FilterStep::new(adjustment));
+                self.history.push(self.state.clone());
+                return self.request_computation();
+            }
+            Interaction::ToggleLinear(linear) => {
+                self.state.linear = linear;
+                self.history.push(self.state.clone());
+                return self.request_computation();
+            }
+            Interaction::UpPunch(punch) => {
+                self.state.punch = punch;
+                return self.request_computation();
             }
-
-            Interaction::Ignored => {}
         }
 
         Command::none()
@@ -295,7 +582,14 @@ impl BlurThing {
                 Some(Message::Interaction(Interaction::CopyToClipboard))
             }
             iced::keyboard::Key::Character("s") => {
-                Some(Message::Interaction(Interaction::ExportImage))
+                Some(Message::Interaction(Interaction::ShowExportOptions))
+            }
+            iced::keyboard::Key::Character("f") => {
+                if modifiers.shift() {
+                    Some(Message::Interaction(Interaction::FlipVertical))
+                } else {
+                    Some(Message::Interaction(Interaction::FlipHorizontal))
+                }
             }
             iced::keyboard::Key::Character("z") => {
                 if modifiers.shift() {
@@ -308,80 +602,147 @@ impl BlurThing {
         }
     }
 
-    fn load_image_file(&mut self, path: PathBuf) -> Result<()> {
-        let loaded = ::image::open(&path).map_err(|e| anyhow!(e.to_string().to_lowercase()))?;
-        self.load_image(loaded);
-        Ok(())
+    /// Decodes and downsamples the image file at the given path in the background.
+    /// Starting a new load cancels the one in progress, if any.
+    fn load_image_file(&mut self, path: PathBuf) -> Command<Message> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        self.start_loading(name, Some(path.clone()), move |is_superseded| {
+            let img = ::image::open(&path).map_err(|e| anyhow!(e.to_string().to_lowercase()))?;
+            // Decoding large files takes a while, so check that the image is still
+            // wanted before spending more time on it.
+            if is_superseded() {
+                anyhow::bail!("image load was cancelled");
+            }
+
+            let pipeline = Pipeline::new(img);
+            let thumbnail = pipeline.thumbnail(PREVIEW_SIZE);
+            Ok((pipeline, thumbnail))
+        })
     }
 
-    fn load_image(&mut self, img: DynamicImage) {
-        // Downsample the image to a smaller size for faster processing.
-        let resized = img.resize_exact(
-            IMAGE_DOWNSAMPLE_SIZE,
-            IMAGE_DOWNSAMPLE_SIZE,
-            FilterType::Lanczos3,
-        );
+    fn load_image(&mut self, img: DynamicImage) -> Command<Message> {
+        self.start_loading(String::from("downloaded image"), None, move |_| {
+            let pipeline = Pipeline::new(img);
+            let thumbnail = pipeline.thumbnail(PREVIEW_SIZE);
+            Ok((pipeline, thumbnail))
+        })
+    }
 
-        // Store the image and reset the parameters to their defaults.
-        self.img = Some(resized);
+    fn start_loading<F>(&mut self, name: String, path: Option<PathBuf>, load: F) -> Command<Message>
+    where
+        F: FnOnce(&dyn Fn() -> bool) -> Result<(Pipeline, DynamicImage)> + Send + 'static,
+    {
+        let id = self.load_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.loading_file = Some(name);
+        self.loading_path = path;
+
+        let load_id = self.load_id.clone();
+        Command::perform(
+            async move { load(&|| load_id.load(Ordering::SeqCst) != id) },
+            move |result| Message::ImageLoaded(id, result),
+        )
+    }
+
+    fn load_pipeline(&mut self, pipeline: Pipeline) -> Command<Message> {
+        // Store the pipeline and reset the parameters to their defaults.
+        self.pipeline = Some(Arc::new(pipeline));
         self.reset_settings();
-        self.computed = Some(self.compute_blurhash(PREVIEW_SIZE).unwrap());
+        self.request_computation()
     }
 
-    fn compute_blurhash(&mut self, size: u32) -> Result<(String, DynamicImage)> {
-        let img = self
-            .img
-            .as_ref()
-            .ok_or_else(|| anyhow!("source image is not available"))?;
+    fn decode_preview(&self, hash: &str) -> Result<DynamicImage> {
+        blurthing_core::decode(hash, PREVIEW_SIZE, PREVIEW_SIZE, self.state.punch)
+    }
 
-        let rotated = match self.state.rotation {
-            1 => &img.rotate90(),
-            2 => &img.rotate180(),
-            3 => &img.rotate270(),
-            _ => img,
-        };
+    /// Schedules the computation of the preview with the current parameters. Only
+    /// one computation runs at a time, and the requests made in the meantime are
+    /// merged into a single one that starts as soon as it finishes.
+    fn request_computation(&mut self) -> Command<Message> {
+        self.computation_id += 1;
+        if self.is_computing {
+            self.is_computation_pending = true;
+            return Command::none();
+        }
+
+        self.compute_and_apply_blurhash()
+    }
+
+    fn compute_and_apply_blurhash(&mut self) -> Command<Message> {
+        self.is_computation_pending = false;
+
+        let pipeline = self.pipeline.clone();
+        let hash = self.computed.as_ref().map(|(hash, _)| hash.clone());
+        if pipeline.is_none() && hash.is_none() {
+            return Command::none();
+        }
+
+        let state = self.state.clone();
+        let id = self.computation_id;
+        self.is_computing = true;
+
+        Command::perform(
+            async move {
+                match (pipeline, hash) {
+                    (Some(pipeline), _) => pipeline.compute(&state, PREVIEW_SIZE),
+                    // Without a source image, only the decoding of the typed hash can change.
+                    (None, Some(hash)) => {
+                        blurthing_core::decode(&hash, PREVIEW_SIZE, PREVIEW_SIZE, state.punch)
+                            .map(|img| (hash, img))
+                    }
+                    (None, None) => Err(anyhow!("source image is not available")),
+                }
+            },
+            move |result| Message::BlurHashComputed(id, result),
+        )
+    }
 
-        let buffer = rotated
-            .blur(self.state.blur as f32)
-            .huerotate(self.state.hue_rotate)
-            .adjust_contrast(self.state.contrast as f32)
-            .brighten(self.state.brightness * 2)
-            .to_rgba8()
-            .to_vec();
-
-        let (width, height) = rotated.dimensions();
-        let (x, y) = self.state.components;
-        // Encode the blurhash and decode it to a preview image for display.
-        let hash = blurhash::encode(x, y, width, height, &buffer)
-            .map_err(|_| anyhow!("failed to compute the blurhash"))?;
-        let decoded = blurhash::decode(&hash, size, size, 1.0)
-            .map_err(|_| anyhow!("failed to decode the computed blurhash"))?;
-        let preview = RgbaImage::from_vec(size, size, decoded)
-            .ok_or_else(|| anyhow!("failed to create preview image from decoded buffer"))?;
-
-        Ok((hash, DynamicImage::ImageRgba8(preview)))
+    fn apply_computed(&mut self, computed: (String, DynamicImage)) {
+        self.hash_input = computed.0.clone();
+        self.hash_error = None;
+        self.computed = Some(computed);
     }
 
-    fn compute_and_apply_blurhash(&mut self) {
-        if self.img.is_none() {
-            return;
+    /// Stores the working resolution, and the matte color of the current image
+    /// file, so that they are used again the next time an image is loaded.
+    fn remember_preferences(&mut self) {
+        let mut is_changed = false;
+        if let Some(path) = &self.source_path {
+            if self.preferences.matte(path) != Some(self.state.matte) {
+                self.preferences.set_matte(path, self.state.matte);
+                is_changed = true;
+            }
+        }
+
+        if self.preferences.downsample_size != self.state.downsample_size
+            || self.preferences.resampling != self.state.resampling
+        {
+            self.preferences.downsample_size = self.state.downsample_size;
+            self.preferences.resampling = self.state.resampling;
+            is_changed = true;
         }
 
-        match self.compute_blurhash(PREVIEW_SIZE) {
-            Ok(hash) => self.computed = Some(hash),
-            Err(e) => {
-                eprintln!("failed to compute blurhash: {}", e);
-                _ = MessageDialog::new()
-                    .set_type(MessageType::Error)
-                    .set_title("Computation Error")
-                    .set_text(&format!("failed to compute blurhash: {}", e))
-                    .show_alert();
+        if is_changed {
+            if let Err(e) = self.preferences.save() {
+                eprintln!("failed to save the preferences: {}", e);
             }
         }
     }
 
     fn reset_settings(&mut self) {
-        self.state = State::default();
+        self.state = State {
+            downsample_size: self.preferences.downsample_size,
+            resampling: self.preferences.resampling,
+            ..State::default()
+        };
+        if let Some(matte) = (self.source_path.as_ref()).and_then(|p| self.preferences.matte(p)) {
+            self.state.matte = matte;
+        }
+        self.matte_input = format_hex_color(self.state.matte);
+
         self.history.reset();
         // Push the initial parameters to the history stack.
         self.history.push(self.state.clone());
@@ -393,15 +754,65 @@ impl BlurThing {
 ///////////////////////////////////////////////
 
 impl BlurThing {
-    fn preview(&self) -> Element<Interaction> {
-        if let Some((_, img)) = &self.computed {
+    fn preview(&self) -> Element<'_, Interaction> {
+        if let (true, Some(thumbnail)) = (self.is_editing_crop, &self.thumbnail) {
+            let (width, height) = (thumbnail.width(), thumbnail.height());
+            let handle = iced::widget::image::Handle::from_pixels(
+                width,
+                height,
+                utils::shade_outside(thumbnail, self.state.crop, self.state.matte).into_raw(),
+            );
+
+            let area = MouseArea::new(
+                Image::new(handle)
+                    .width(Length::Fixed(width as f32))
+                    .height(Length::Fixed(height as f32)),
+            )
+            .on_move(Interaction::MoveCropCursor)
+            .on_press(Interaction::StartCrop)
+            .interaction(iced::mouse::Interaction::Crosshair);
+
+            Container::new(area)
+                .style(styles::Container::Medium)
+                .height(Length::Fixed(PREVIEW_SIZE as f32))
+                .width(Length::Fixed(PREVIEW_SIZE as f32))
+                .center_x()
+                .center_y()
+                .into()
+        } else if let Some(name) = &self.loading_file {
+            Container::new(
+                Text::new(format!("Loading {}…", name))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .horizontal_alignment(Horizontal::Center)
+                    .vertical_alignment(Vertical::Center),
+            )
+            .style(styles::Container::Busy)
+            .height(Length::Fixed(PREVIEW_SIZE as f32))
+            .width(Length::Fixed(PREVIEW_SIZE as f32))
+            .padding(32)
+            .into()
+        } else if let Some((_, img)) = &self.computed {
             let handle = iced::widget::image::Handle::from_pixels(
                 img.width(),
                 img.height(),
                 img.to_rgba8().to_vec(),
             );
 
-            Image::new(handle).into()
+            // Center the preview, since only its longest side fills the pane.
+            let style = if self.is_computing {
+                styles::Container::Busy
+            } else {
+                styles::Container::Light
+            };
+
+            Container::new(Image::new(handle))
+                .style(style)
+                .height(Length::Fixed(PREVIEW_SIZE as f32))
+                .width(Length::Fixed(PREVIEW_SIZE as f32))
+                .center_x()
+                .center_y()
+                .into()
         } else {
             Container::new(
                 Text::new("Press on \"Select File\" or drop an image here to get started")
@@ -418,7 +829,7 @@ impl BlurThing {
         }
     }
 
-    fn header(&self) -> Element<Interaction> {
+    fn header(&self) -> Element<'_, Interaction> {
         let mut feeling_lucky = Button::new("I'm Feeling Lucky");
         if !self.is_downloading_image {
             feeling_lucky = feeling_lucky.on_press(Interaction::ImFeelingLucky);
@@ -441,7 +852,7 @@ impl BlurThing {
             .into()
     }
 
-    fn controls(&self) -> Element<Interaction> {
+    fn controls(&self) -> Element<'_, Interaction> {
         let x_components = Column::new()
             .push(Text::new("X Components"))
             .push(
@@ -466,6 +877,106 @@ impl BlurThing {
                     .on_release(Interaction::SaveParameters),
             );
 
+        let selected_style = |is_selected: bool| {
+            if is_selected {
+                styles::Button::Primary
+            } else {
+                styles::Button::Default
+            }
+        };
+
+        let mut sizes = Row::new().spacing(4);
+        for size in DOWNSAMPLE_PRESETS {
+            sizes = sizes.push(
+                Button::new(Text::new(size.to_string()).size(12))
+                    .style(selected_style(self.state.downsample_size == size))
+                    .on_press(Interaction::UpDownsampleSize(size)),
+            );
+        }
+
+        let mut resamplings = Row::new().spacing(4);
+        for resampling in Resampling::ALL {
+            resamplings = resamplings.push(
+                Button::new(Text::new(resampling.name()).size(12))
+                    .style(selected_style(self.state.resampling == resampling))
+                    .on_press(Interaction::UpResampling(resampling)),
+            );
+        }
+
+        let working_resolution = Column::new()
+            .push(Text::new("Working Resolution"))
+            .push(
+                Text::new("Size and filter the image is downsampled with before being hashed")
+                    .style(styles::Text::Subtle)
+                    .size(12),
+            )
+            .push(sizes)
+            .push(resamplings)
+            .spacing(4);
+
+        let mut reset_crop = Button::new("Reset");
+        if self.state.crop.is_some() {
+            reset_crop = reset_crop.on_press(Interaction::ResetCrop);
+        }
+
+        let crop = Row::new()
+            .push(
+                Column::new().push(Text::new("Crop")).push(
+                    Text::new("Drag over the source image to hash only a region of it")
+                        .style(styles::Text::Subtle)
+                        .size(12),
+                ),
+            )
+            .push(Space::with_width(Length::Fill))
+            .push(
+                Button::new(if self.is_editing_crop { "Done" } else { "Edit" })
+                    .on_press_maybe(self.thumbnail.as_ref().map(|_| Interaction::EditCrop)),
+            )
+            .push(reset_crop)
+            .align_items(iced::Alignment::Center)
+            .spacing(8);
+
+        // The source image is shown over the swatch, so that its transparent areas
+        // preview the matte.
+        let swatch: Element<'_, Interaction> = match &self.thumbnail {
+            Some(thumbnail) => Image::new(iced::widget::image::Handle::from_pixels(
+                thumbnail.width(),
+                thumbnail.height(),
+                thumbnail.to_rgba8().into_raw(),
+            ))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into(),
+            None => Space::new(Length::Fill, Length::Fill).into(),
+        };
+
+        let [r, g, b] = self.state.matte;
+        let matte = Row::new()
+            .push(
+                Column::new().push(Text::new("Matte")).push(
+                    Text::new("Color that transparent areas are composited over")
+                        .style(styles::Text::Subtle)
+                        .size(12),
+                ),
+            )
+            .push(Space::with_width(Length::Fill))
+            .push(
+                Container::new(swatch)
+                    .style(styles::Container::Swatch(iced::Color::from_rgb8(r, g, b)))
+                    .width(Length::Fixed(48.0))
+                    .height(Length::Fixed(48.0))
+                    .padding(4)
+                    .center_x()
+                    .center_y(),
+            )
+            .push(
+                TextInput::new("#ffffff", &self.matte_input)
+                    .on_input(Interaction::UpMatte)
+                    .width(Length::Fixed(96.0)),
+            )
+            .align_items(iced::Alignment::Center)
+            .spacing(8);
+
         let rotate = Row::new()
             .push(
                 Column::new().push(Text::new("Rotate")).push(
@@ -480,70 +991,378 @@ impl BlurThing {
             .spacing(8)
             .padding([0, 0, 4, 0]);
 
-        let smoothness = Column::new()
-            .push(Text::new("Smoothness"))
+        let flip_button = |label: &'static str, is_flipped: bool, interaction: Interaction| {
+            let style = if is_flipped {
+                styles::Button::Primary
+            } else {
+                styles::Button::Default
+            };
+            Button::new(label).style(style).on_press(interaction)
+        };
+
+        let flip = Row::new()
             .push(
-                Text::new("Amount of blur applied before the hash is computed")
+                Column::new().push(Text::new("Flip")).push(
+                    Text::new("Mirror the input image, for right-to-left layouts")
+                        .style(styles::Text::Subtle)
+                        .size(12),
+                ),
+            )
+            .push(Space::with_width(Length::Fill))
+            .push(flip_button(
+                "⇆",
+                self.state.flip_horizontal,
+                Interaction::FlipHorizontal,
+            ))
+            .push(flip_button(
+                "⇅",
+                self.state.flip_vertical,
+                Interaction::FlipVertical,
+            ))
+            .spacing(8)
+            .padding([0, 0, 4, 0]);
+
+        let aspect_button = |label: String, aspect: Option<Aspect>| {
+            let style = if self.state.aspect == aspect {
+                styles::Button::Primary
+            } else {
+                styles::Button::Default
+            };
+            Button::new(Text::new(label).size(14))
+                .style(style)
+                .on_press(Interaction::UpAspect(aspect))
+        };
+        let fit_button = |label: &'static str, fit: Fit| {
+            let style = if self.state.fit == fit {
+                styles::Button::Primary
+            } else {
+                styles::Button::Default
+            };
+            Button::new(Text::new(label).size(14))
+                .style(style)
+                .on_press_maybe(self.state.aspect.map(|_| Interaction::UpFit(fit)))
+        };
+
+        let mut aspects = Row::new()
+            .push(aspect_button(String::from("Source"), None))
+            .spacing(4);
+        for aspect in ASPECT_PRESETS {
+            let label = format!("{}:{}", aspect.width, aspect.height);
+            aspects = aspects.push(aspect_button(label, Some(aspect)));
+        }
+
+        let aspect = Column::new()
+            .push(Text::new("Aspect Ratio"))
+            .push(
+                Text::new("Shape of the slot the placeholder fills, with bars in the matte color")
                     .style(styles::Text::Subtle)
                     .size(12),
             )
+            .push(aspects)
             .push(
-                Slider::new(0..=32, self.state.blur, Interaction::UpBlur)
-                    .on_release(Interaction::SaveParameters),
-            );
+                Row::new()
+                    .push(fit_button("Cover", Fit::Cover))
+                    .push(fit_button("Contain", Fit::Contain))
+                    .spacing(4),
+            )
+            .spacing(4);
 
-        let hue_rotation = Column::new()
-            .push(Text::new("Hue Rotation"))
+        let linear = Column::new()
             .push(
-                Text::new("How much to rotate the hue of the image (color shift)")
+                Checkbox::new("Linear Light", self.state.linear)
+                    .on_toggle(Interaction::ToggleLinear),
+            )
+            .push(
+                Text::new("Applies the adjustments in linear light, so that blurring keeps the brightness")
                     .style(styles::Text::Subtle)
                     .size(12),
             )
-            .push(
-                Slider::new(-180..=180, self.state.hue_rotate, Interaction::UpHue)
-                    .on_release(Interaction::SaveParameters),
-            );
+            .spacing(4);
 
-        let brightness = Column::new()
-            .push(Text::new("Brightness"))
+        let punch = Column::new()
+            .push(Text::new("Punch"))
             .push(
-                Text::new("Adjusts the overall lightness or darkness of the image")
+                Text::new("Boosts or softens the contrast of the decoded image, not of the hash")
                     .style(styles::Text::Subtle)
                     .size(12),
             )
             .push(
-                Slider::new(-100..=100, self.state.brightness, Interaction::UpBrightness)
+                Slider::new(0.1..=4.0, self.state.punch, Interaction::UpPunch)
+                    .step(0.1)
                     .on_release(Interaction::SaveParameters),
             );
 
-        let contrast = Column::new()
-            .push(Text::new("Contrast"))
+        Column::new()
+            .push(x_components)
+            .push(y_components)
+            .push(working_resolution)
+            .push(crop)
+            .push(matte)
+            .push(rotate)
+            .push(flip)
+            .push(aspect)
+            .push(self.filters())
+            .push(linear)
+            .push(punch)
+            .padding(24)
+            .spacing(8)
+            .into()
+    }
+
+    fn filters(&self) -> Element<'_, Interaction> {
+        let mut filters = Column::new()
+            .push(Text::new("Filters"))
             .push(
-                Text::new(
-                    "Modifies the difference between the darkest and lightest parts of the image",
-                )
+                Text::new("Adjustments applied to the image from top to bottom")
+                    .style(styles::Text::Subtle)
+                    .size(12),
+            )
+            .spacing(8);
+
+        for (index, step) in self.state.filters.iter().enumerate() {
+            filters = filters.push(self.filter_step(index, step));
+        }
+
+        // The buttons to add filters are split in rows, to fit in the panel.
+        filters = filters.push(
+            Text::new("Add a filter")
                 .style(styles::Text::Subtle)
                 .size(12),
+        );
+        for chunk in Adjustment::ALL.chunks(3) {
+            let mut row = Row::new().spacing(4);
+            for &adjustment in chunk {
+                row = row.push(
+                    Button::new(
+                        Text::new(adjustment.name())
+                            .size(12)
+                            .width(Length::Fill)
+                            .horizontal_alignment(Horizontal::Center),
+                    )
+                    .width(Length::Fill)
+                    .on_press(Interaction::AddFilter(adjustment)),
+                );
+            }
+            filters = filters.push(row);
+        }
+
+        filters.into()
+    }
+
+    fn filter_step(&self, index: usize, step: &FilterStep) -> Element<'_, Interaction> {
+        let last = self.state.filters.len() - 1;
+        let header = Row::new()
+            .push(
+                Checkbox::new(step.adjustment.name(), step.enabled)
+                    .on_toggle(move |enabled| Interaction::ToggleFilter(index, enabled)),
             )
+            .push(Space::with_width(Length::Fill))
             .push(
-                Slider::new(-40..=220, self.state.contrast, Interaction::UpContrast)
-                    .on_release(Interaction::SaveParameters),
-            );
+                Button::new(Text::new("▲").size(12))
+                    .on_press_maybe((index > 0).then(|| Interaction::MoveFilter(index, index - 1))),
+            )
+            .push(
+                Button::new(Text::new("▼").size(12)).on_press_maybe(
+                    (index < last).then(|| Interaction::MoveFilter(index, index + 1)),
+                ),
+            )
+            .push(Button::new(Text::new("✕").size(12)).on_press(Interaction::RemoveFilter(index)))
+            .align_items(iced::Alignment::Center)
+            .spacing(4);
+
+        let description = |text: &'static str| Text::new(text).style(styles::Text::Subtle).size(12);
+        let slider = |range, value, to_adjustment: fn(i32) -> Adjustment| {
+            Slider::new(range, value, move |value| {
+                Interaction::UpFilter(index, to_adjustment(value))
+            })
+            .on_release(Interaction::SaveParameters)
+        };
+
+        let controls: Element<Interaction> = match step.adjustment {
+            Adjustment::Blur(blur) => {
+                let mut methods = Row::new().spacing(4);
+                for method in Smoothing::ALL {
+                    let style = if blur.method == method {
+                        styles::Button::Primary
+                    } else {
+                        styles::Button::Default
+                    };
+                    methods = methods.push(
+                        Button::new(Text::new(method.name()).size(12))
+                            .style(style)
+                            .on_press(Interaction::SetFilter(
+                                index,
+                                Adjustment::Blur(Blur {
+                                    method,
+                                    sigma: blur.sigma.min(max_blur(method)),
+                                }),
+                            )),
+                    );
+                }
+
+                let text = match blur.method {
+                    Smoothing::Gaussian => "Amount of blur applied before the hash is computed",
+                    _ => "Radius of the smoothing in source pixels, keeping the edges sharp",
+                };
+                let sigma = Slider::new(0..=max_blur(blur.method), blur.sigma, move |sigma| {
+                    Interaction::UpFilter(index, Adjustment::Blur(Blur { sigma, ..blur }))
+                })
+                .on_release(Interaction::SaveParameters);
+
+                Column::new()
+                    .push(description(text))
+                    .push(methods)
+                    .push(sigma)
+                    .spacing(4)
+                    .into()
+            }
+            Adjustment::HueRotate(hue) => Column::new()
+                .push(description(
+                    "How much to rotate the hue of the image (color shift)",
+                ))
+                .push(slider(-180..=180, hue.degrees, |degrees| {
+                    Adjustment::HueRotate(HueRotate { degrees })
+                }))
+                .into(),
+            Adjustment::Contrast(contrast) => Column::new()
+                .push(description(
+                    "Modifies the difference between the darkest and lightest parts of the image",
+                ))
+                .push(slider(-40..=220, contrast.amount, |amount| {
+                    Adjustment::Contrast(Contrast { amount })
+                }))
+                .into(),
+            Adjustment::Brightness(brightness) => Column::new()
+                .push(description(
+                    "Adjusts the overall lightness or darkness of the image",
+                ))
+                .push(slider(-100..=100, brightness.amount, |amount| {
+                    Adjustment::Brightness(Brightness { amount })
+                }))
+                .into(),
+            Adjustment::Saturation(saturation) => Column::new()
+                .push(description(
+                    "Makes the colors more vivid, or fades them towards grayscale",
+                ))
+                .push(slider(-100..=100, saturation.amount, |amount| {
+                    Adjustment::Saturation(Saturation { amount })
+                }))
+                .into(),
+            Adjustment::WhiteBalance(balance) => {
+                // Each slider only changes its own setting, keeping the other one.
+                let temperature =
+                    Slider::new(-100..=100, balance.temperature, move |temperature| {
+                        Interaction::UpFilter(
+                            index,
+                            Adjustment::WhiteBalance(WhiteBalance {
+                                temperature,
+                                ..balance
+                            }),
+                        )
+                    })
+                    .on_release(Interaction::SaveParameters);
+                let tint = Slider::new(-100..=100, balance.tint, move |tint| {
+                    Interaction::UpFilter(
+                        index,
+                        Adjustment::WhiteBalance(WhiteBalance { tint, ..balance }),
+                    )
+                })
+                .on_release(Interaction::SaveParameters);
+
+                Column::new()
+                    .push(description(
+                        "Warms up the image towards yellow, or cools it down towards blue",
+                    ))
+                    .push(temperature)
+                    .push(description(
+                        "Shifts the colors of the image towards green or magenta",
+                    ))
+                    .push(tint)
+                    .into()
+            }
+        };
+
+        Column::new().push(header).push(controls).into()
+    }
+
+    fn export_options(&self) -> Element<'_, Interaction> {
+        let options = &self.export_options;
+        let source = self
+            .pipeline
+            .as_ref()
+            .map(|p| p.output_dimensions(&self.state));
+
+        let match_source_label = match source {
+            Some((width, height)) => format!("Match source ({}×{})", width, height),
+            None => String::from("Match source"),
+        };
+        let match_source =
+            Checkbox::new(match_source_label, options.match_source && source.is_some())
+                .on_toggle_maybe(source.map(|_| Interaction::ToggleMatchSource))
+                .text_size(14);
+
+        let mut width = TextInput::new("Width", &options.width);
+        let mut height = TextInput::new("Height", &options.height);
+        if !options.match_source || source.is_none() {
+            width = width.on_input(Interaction::UpExportWidth);
+            height = height.on_input(Interaction::UpExportHeight);
+        }
+
+        let size = Column::new()
+            .push(Text::new("Size"))
+            .push(
+                Text::new("Resolution of the exported image, in pixels")
+                    .style(styles::Text::Subtle)
+                    .size(12),
+            )
+            .push(match_source)
+            .push(
+                Row::new()
+                    .push(width)
+                    .push(Text::new("×"))
+                    .push(height)
+                    .align_items(iced::Alignment::Center)
+                    .spacing(8),
+            )
+            .spacing(4);
+
+        let quality = Column::new()
+            .push(Text::new(format!("JPEG Quality ({})", options.quality)))
+            .push(
+                Text::new("Only used for JPEG files, PNG and WebP images are lossless")
+                    .style(styles::Text::Subtle)
+                    .size(12),
+            )
+            .push(Slider::new(
+                1..=100,
+                options.quality,
+                Interaction::UpExportQuality,
+            ));
+
+        let actions = Row::new()
+            .push(Space::with_width(Length::Fill))
+            .push(Button::new("Cancel").on_press(Interaction::HideExportOptions))
+            .push(
+                Button::new(if self.is_exporting {
+                    "Exporting…"
+                } else {
+                    "Export"
+                })
+                .style(styles::Button::Primary)
+                .on_press_maybe((!self.is_exporting).then_some(Interaction::ExportImage)),
+            )
+            .spacing(8);
 
         Column::new()
-            .push(x_components)
-            .push(y_components)
-            .push(rotate)
-            .push(smoothness)
-            .push(hue_rotation)
-            .push(brightness)
-            .push(contrast)
+            .push(size)
+            .push(quality)
+            .push(actions)
             .padding(24)
             .spacing(8)
             .into()
     }
 
-    fn footer(&self) -> Element<Interaction> {
+    fn footer(&self) -> Element<'_, Interaction> {
         let select_file = Button::new(
             Text::new("Select File")
                 .width(Length::Fill)
@@ -553,29 +1372,34 @@ impl BlurThing {
 
         let mut export_image = Button::new("Export Image").style(styles::Button::Primary);
         if self.computed.is_some() {
-            export_image = export_image.on_press(Interaction::ExportImage)
+            export_image = export_image.on_press(Interaction::ShowExportOptions)
         }
 
-        let hash_string = self
-            .computed
-            .as_ref()
-            .map(|(hash, _)| hash.clone())
-            .unwrap_or_default();
-        let mut out_hash = TextInput::new("Load an image to compute its hash", &hash_string);
-        if self.computed.is_some() {
-            out_hash = out_hash.on_input(|_| Interaction::Ignored);
-        }
+        let out_hash = TextInput::new("Load an image or paste a hash", &self.hash_input)
+            .on_input(Interaction::UpHash);
 
         let mut copy_to_clipboard = Button::new("Copy to Clipboard").style(styles::Button::Primary);
         if self.computed.is_some() {
             copy_to_clipboard = copy_to_clipboard.on_press(Interaction::CopyToClipboard)
         }
 
-        Column::new()
+        let mut footer = Column::new()
             .push(Row::new().push(select_file).push(export_image).spacing(8))
-            .push(Row::new().push(out_hash).push(copy_to_clipboard).spacing(8))
-            .padding(16)
-            .spacing(8)
-            .into()
+            .push(Row::new().push(out_hash).push(copy_to_clipboard).spacing(8));
+        if let Some(error) = &self.hash_error {
+            footer = footer.push(Text::new(error).style(styles::Text::Error).size(12));
+        }
+
+        footer.padding(16).spacing(8).into()
+    }
+}
+
+/// Largest setting of the smoothing slider for the given method. Edge-preserving
+/// methods are measured on the source image, usually much larger than the
+/// downsampled one.
+fn max_blur(method: Smoothing) -> i32 {
+    match method {
+        Smoothing::Gaussian => 32,
+        Smoothing::Bilateral | Smoothing::Median => 128,
     }
 }
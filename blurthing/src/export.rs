@@ -0,0 +1,50 @@
+use anyhow::{anyhow, Result};
+
+/// Largest width or height of the exported images.
+pub const EXPORT_MAX_SIZE: u32 = 16384;
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub match_source: bool,
+    pub width: String,
+    pub height: String,
+    pub quality: u8,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            match_source: true,
+            width: String::from("1920"),
+            height: String::from("1080"),
+            quality: 90,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Returns the dimensions of the exported image, using the ones of the source
+    /// image when requested and available, scaled down to the largest size allowed.
+    pub fn dimensions(&self, source: Option<(u32, u32)>) -> Result<(u32, u32)> {
+        if let (true, Some((width, height))) = (self.match_source, source) {
+            if width.max(height) > EXPORT_MAX_SIZE {
+                return Ok(blurthing_core::fit_dimensions(
+                    (width, height),
+                    EXPORT_MAX_SIZE,
+                ));
+            }
+            return Ok((width, height));
+        }
+
+        let parse = |value: &str, name: &str| {
+            value
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|v| (1..=EXPORT_MAX_SIZE).contains(v))
+                .ok_or_else(|| anyhow!("{} must be between 1 and {}", name, EXPORT_MAX_SIZE))
+        };
+
+        Ok((parse(&self.width, "width")?, parse(&self.height, "height")?))
+    }
+}
@@ -6,7 +6,9 @@ use iced::{Application, Settings, Size};
 use application::{BlurThing, PREVIEW_SIZE};
 
 mod application;
+mod export;
 mod message;
+mod preferences;
 mod state;
 mod styles;
 mod undo_history;
@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use ::image::DynamicImage;
 use anyhow::Result;
+use blurthing_core::{Adjustment, Aspect, Fit, Pipeline, Resampling};
+use iced::Point;
 
 #[derive(Debug)]
 pub enum Message {
@@ -9,6 +11,9 @@ pub enum Message {
 
     FileDropped(PathBuf),
     ImageDownloaded(Result<DynamicImage>),
+    ImageLoaded(u64, Result<(Pipeline, DynamicImage)>),
+    BlurHashComputed(u64, Result<(String, DynamicImage)>),
+    ImageExported(Result<()>),
 }
 
 #[derive(Debug, Clone)]
@@ -17,22 +22,42 @@ pub enum Interaction {
     Redo,
 
     SelectImage,
+    ShowExportOptions,
+    HideExportOptions,
     ExportImage,
+    ToggleMatchSource(bool),
+    UpExportWidth(String),
+    UpExportHeight(String),
+    UpExportQuality(u8),
 
     CopyToClipboard,
+    UpHash(String),
     ImFeelingLucky,
     OpenProjectRepo,
 
     SaveParameters,
     UpX(u32),
     UpY(u32),
+    UpDownsampleSize(u32),
+    UpResampling(Resampling),
+    UpMatte(String),
+    EditCrop,
+    ResetCrop,
+    MoveCropCursor(Point),
+    StartCrop,
+    EndCrop,
     RotateCW,
     RotateCCW,
-    UpBlur(i32),
-    UpHue(i32),
-    UpBrightness(i32),
-    UpContrast(i32),
-
-    // Command to do nothing, useful to enable TextInputs even when read only.
-    Ignored,
+    FlipHorizontal,
+    FlipVertical,
+    UpAspect(Option<Aspect>),
+    UpFit(Fit),
+    UpFilter(usize, Adjustment),
+    SetFilter(usize, Adjustment),
+    ToggleFilter(usize, bool),
+    MoveFilter(usize, usize),
+    RemoveFilter(usize),
+    AddFilter(Adjustment),
+    ToggleLinear(bool),
+    UpPunch(f32),
 }
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use blurthing_core::{Resampling, IMAGE_DOWNSAMPLE_SIZE};
+use serde::{Deserialize, Serialize};
+
+/// Choices that are remembered between runs of the application.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Matte color last used for each image, by canonical path.
+    pub mattes: HashMap<String, [u8; 3]>,
+    /// Size the images are downsampled to, applied to every image that is loaded.
+    pub downsample_size: u32,
+    /// Filter used to downsample the images.
+    pub resampling: Resampling,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            mattes: HashMap::new(),
+            downsample_size: IMAGE_DOWNSAMPLE_SIZE,
+            resampling: Resampling::default(),
+        }
+    }
+}
+
+impl Preferences {
+    /// Loads the preferences from the configuration directory, falling back to
+    /// the defaults when they were never saved or cannot be read.
+    pub fn load() -> Self {
+        let Ok(path) = Self::path() else {
+            return Self::default();
+        };
+
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("failed to parse the preferences: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn matte(&self, image: &Path) -> Option<[u8; 3]> {
+        self.mattes.get(&Self::key(image)).copied()
+    }
+
+    pub fn set_matte(&mut self, image: &Path, matte: [u8; 3]) {
+        self.mattes.insert(Self::key(image), matte);
+    }
+
+    fn key(image: &Path) -> String {
+        let path = image.canonicalize().unwrap_or_else(|_| image.to_path_buf());
+        path.to_string_lossy().into_owned()
+    }
+
+    fn path() -> Result<PathBuf> {
+        let dir = dirs_next::config_dir()
+            .ok_or_else(|| anyhow!("the configuration directory is not available"))?;
+        Ok(dir.join("blurthing").join("preferences.json"))
+    }
+}
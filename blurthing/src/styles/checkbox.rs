@@ -0,0 +1,55 @@
+use iced::widget::checkbox::{Appearance, StyleSheet};
+use iced::Border;
+
+use super::Theme;
+
+impl StyleSheet for Theme {
+    type Style = ();
+
+    fn active(&self, _style: &Self::Style, is_checked: bool) -> Appearance {
+        Appearance {
+            background: if is_checked {
+                self.palette.primary_100.into()
+            } else {
+                self.palette.base_200.into()
+            },
+            icon_color: self.palette.primary_content,
+            border: Border {
+                color: if is_checked {
+                    self.palette.primary_500
+                } else {
+                    self.palette.base_400
+                },
+                width: 1.0,
+                radius: 4.0.into(),
+            },
+            text_color: None,
+        }
+    }
+
+    fn hovered(&self, _style: &Self::Style, is_checked: bool) -> Appearance {
+        let base = self.active(_style, is_checked);
+
+        Appearance {
+            border: Border {
+                color: self.palette.primary_200,
+                ..base.border
+            },
+            ..base
+        }
+    }
+
+    fn disabled(&self, _style: &Self::Style, is_checked: bool) -> Appearance {
+        let base = self.active(_style, is_checked);
+
+        Appearance {
+            background: self.palette.base_disabled.into(),
+            icon_color: self.palette.base_content_disabled,
+            border: Border {
+                color: self.palette.base_200,
+                ..base.border
+            },
+            text_color: Some(self.palette.base_content_disabled),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use iced::widget::container::{Appearance, StyleSheet};
+use iced::{Border, Color};
 
 use super::Theme;
 
@@ -7,6 +8,10 @@ pub enum Container {
     #[default]
     Light,
     Medium,
+    // Light container with a highlighted border, shown while work is in progress.
+    Busy,
+    // Solid sample of a color picked by the user.
+    Swatch(Color),
 }
 
 impl StyleSheet for Theme {
@@ -14,12 +19,28 @@ impl StyleSheet for Theme {
 
     fn appearance(&self, style: &Self::Style) -> Appearance {
         let background = match style {
-            Container::Light => self.palette.base_100.into(),
+            Container::Light | Container::Busy => self.palette.base_100.into(),
             Container::Medium => self.palette.base_200.into(),
+            Container::Swatch(color) => (*color).into(),
+        };
+
+        let border = match style {
+            Container::Busy => Border {
+                color: self.palette.primary_300,
+                width: 2.0,
+                radius: 0.0.into(),
+            },
+            Container::Swatch(_) => Border {
+                color: self.palette.base_300,
+                width: 1.0,
+                radius: 4.0.into(),
+            },
+            _ => Border::default(),
         };
 
         Appearance {
             background: Some(background),
+            border,
             ..Default::default()
         }
     }
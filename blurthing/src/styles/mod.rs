@@ -1,5 +1,6 @@
 mod application;
 mod button;
+mod checkbox;
 mod container;
 mod scrollable;
 mod slider;
@@ -33,6 +34,8 @@ pub struct Palette {
     pub primary_disabled: Color,
     pub primary_content: Color,
     pub primary_content_disabled: Color,
+
+    pub error: Color,
 }
 
 impl Palette {
@@ -56,6 +59,8 @@ impl Palette {
         primary_disabled: color!(0x0c2531),
         primary_content: color!(0xffffff),
         primary_content_disabled: color!(0x297aa3),
+
+        error: color!(0xe06c6c),
     };
 }
 
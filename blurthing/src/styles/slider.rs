@@ -37,7 +37,6 @@ impl StyleSheet for Theme {
                 color: self.palette.primary_500,
                 ..base.handle
             },
-            ..base
         }
     }
 
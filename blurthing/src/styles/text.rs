@@ -7,6 +7,7 @@ pub enum Text {
     #[default]
     Regular,
     Subtle,
+    Error,
 }
 
 impl StyleSheet for Theme {
@@ -17,6 +18,7 @@ impl StyleSheet for Theme {
             color: match style {
                 Text::Regular => None,
                 Text::Subtle => Some(self.palette.base_content_subtle),
+                Text::Error => Some(self.palette.error),
             },
         }
     }
@@ -1,7 +1,29 @@
-use ::image::DynamicImage;
+use ::image::{DynamicImage, RgbaImage};
 use anyhow::Result;
+use blurthing_core::Crop;
 
 pub fn download_random_image() -> Result<DynamicImage> {
     let bytes = reqwest::blocking::get("https://picsum.photos/512")?.bytes()?;
     ::image::load_from_memory(&bytes).map_err(Into::into)
 }
+
+/// Composites an image over the matte color, the way it is hashed, and darkens
+/// the parts that are outside of the crop region, so that the region stands out
+/// while it is being selected.
+pub fn shade_outside(img: &DynamicImage, crop: Option<Crop>, matte: [u8; 3]) -> RgbaImage {
+    let mut buffer = blurthing_core::flatten(img, matte).to_rgba8();
+    let Some(crop) = crop else {
+        return buffer;
+    };
+
+    let (x, y, width, height) = crop.pixels(buffer.dimensions());
+    for (px, py, pixel) in buffer.enumerate_pixels_mut() {
+        if px < x || py < y || px >= x + width || py >= y + height {
+            for c in &mut pixel.0[..3] {
+                *c /= 3;
+            }
+        }
+    }
+
+    buffer
+}